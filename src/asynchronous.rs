@@ -0,0 +1,133 @@
+use crate::{
+    check_range, open_error, proto, B15FCommandError, B15FInitError, Port, BAUD,
+    DEFAULT_ANALOG_CHANNELS, DEFAULT_TIMEOUT,
+};
+use rand::random;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+/// An async version of [`crate::B15F`] for use inside a tokio runtime, e.g. in GUIs or web backends.
+///
+/// The requests and responses are the same as for the blocking board, only the IO is awaited, so
+/// reading the board doesn't block the executor. It offers the basic commands only.
+///
+/// Every command fails with [`B15FCommandError::Timeout`] if the board doesn't answer within
+/// [`AsyncB15F::timeout`], which needs a runtime with the time driver enabled.
+pub struct AsyncB15F<P = SerialStream>
+where
+    P: AsyncRead + AsyncWrite + Unpin,
+{
+    port: P,
+    timeout: Duration,
+}
+
+impl AsyncB15F<SerialStream> {
+    /// Opens the board on the given port, must be called inside a tokio runtime.
+    pub async fn open_port(port_name: &str) -> Result<AsyncB15F<SerialStream>, B15FInitError> {
+        let port = tokio_serial::new(port_name, BAUD)
+            .open_native_async()
+            .map_err(|err| open_error(port_name, err))?;
+        AsyncB15F::from(port).await
+    }
+}
+
+impl<P> AsyncB15F<P>
+where
+    P: AsyncRead + AsyncWrite + Unpin,
+{
+    pub async fn from(port: P) -> Result<AsyncB15F<P>, B15FInitError> {
+        let mut board = AsyncB15F {
+            port,
+            timeout: DEFAULT_TIMEOUT,
+        };
+        let pass = board.test().await?;
+        if !pass {
+            return Err(B15FInitError::DeviceNotSupported);
+        }
+        Ok(board)
+    }
+
+    /// Sets how long a command may take until it fails, 5 seconds by default like the blocking board.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns how long a command may take until it fails.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    async fn transact(
+        &mut self,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<(), B15FCommandError> {
+        let port = &mut self.port;
+        let io = async {
+            port.write_all(request).await?;
+            port.flush().await?;
+            port.read_exact(response).await?;
+            Ok(())
+        };
+        tokio::time::timeout(self.timeout, io)
+            .await
+            .unwrap_or_else(|_| {
+                Err(B15FCommandError::Timeout(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("board did not answer within {:?}", self.timeout),
+                )))
+            })
+    }
+
+    async fn command_ok(&mut self, request: &[u8]) -> Result<(), B15FCommandError> {
+        let mut response = [0u8];
+        self.transact(request, &mut response).await?;
+        proto::decode_status(&response)
+    }
+
+    /// See [`crate::B15F::test`].
+    pub async fn test(&mut self) -> Result<bool, B15FCommandError> {
+        let rand = random::<u8>();
+        let mut response = [0u8; 2];
+        self.transact(&proto::encode_test(rand), &mut response)
+            .await?;
+        proto::decode_test(&response, rand)
+    }
+
+    /// See [`crate::B15F::digital_write`].
+    pub async fn digital_write(&mut self, port: Port, value: u8) -> Result<(), B15FCommandError> {
+        self.command_ok(&proto::encode_digital_write(port, value))
+            .await
+    }
+
+    /// See [`crate::B15F::digital_read`].
+    pub async fn digital_read(&mut self, port: Port) -> Result<u8, B15FCommandError> {
+        let mut response = [0u8];
+        self.transact(&proto::encode_digital_read(port), &mut response)
+            .await?;
+        proto::decode_digital(&response)
+    }
+
+    /// See [`crate::B15F::analog_write`].
+    pub async fn analog_write(&mut self, port: Port, value: u16) -> Result<(), B15FCommandError> {
+        check_range(value, 1023)?;
+        self.command_ok(&proto::encode_analog_write(port, value))
+            .await
+    }
+
+    /// See [`crate::B15F::analog_read`], the channel must be between 0 and 7.
+    pub async fn analog_read(&mut self, channel: u8) -> Result<u16, B15FCommandError> {
+        if channel >= DEFAULT_ANALOG_CHANNELS {
+            return Err(B15FCommandError::InvalidChannel {
+                channel,
+                valid: 0..=DEFAULT_ANALOG_CHANNELS - 1,
+            });
+        }
+        let mut response = [0u8; 2];
+        self.transact(&proto::encode_analog_read(channel), &mut response)
+            .await?;
+        proto::decode_analog(&response)
+    }
+}
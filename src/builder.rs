@@ -0,0 +1,181 @@
+use crate::{
+    maybe_b15f, port_priority, B15FInitError, BitOrder, DiscoveredBoards, NativePort, RetryPolicy,
+    B15F, BAUD, DEFAULT_TIMEOUT,
+};
+use std::time::Duration;
+
+/// Opens a board with custom connection options, see [`B15F::builder`].
+///
+/// ```no_run
+/// use b15f::B15F;
+/// use std::time::Duration;
+///
+/// let board = B15F::builder()
+///     .port_name("/dev/ttyUSB0")
+///     .timeout(Duration::from_secs(1))
+///     .open()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct B15FBuilder {
+    port_name: Option<String>,
+    baud: u32,
+    timeout: Duration,
+    skip_test: bool,
+    retry: RetryPolicy,
+    reset_on_drop: bool,
+    bit_order: BitOrder,
+    command_delay: Duration,
+    auto_flush: bool,
+    read_timeout: Option<Duration>,
+}
+
+impl Default for B15FBuilder {
+    fn default() -> Self {
+        B15FBuilder {
+            port_name: None,
+            baud: BAUD,
+            timeout: DEFAULT_TIMEOUT,
+            skip_test: false,
+            retry: RetryPolicy::default(),
+            reset_on_drop: false,
+            bit_order: BitOrder::Lsb,
+            command_delay: Duration::ZERO,
+            auto_flush: true,
+            read_timeout: None,
+        }
+    }
+}
+
+impl B15FBuilder {
+    /// The serial port to open, without one [`B15FBuilder::open`] discovers the board.
+    pub fn port_name(mut self, port_name: &str) -> Self {
+        self.port_name = Some(port_name.to_string());
+        self
+    }
+
+    /// The baud rate, 57600 by default.
+    pub fn baud(mut self, baud: u32) -> Self {
+        self.baud = baud;
+        self
+    }
+
+    /// The read/write timeout, 5 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Skips the test request after opening the port, e.g. to talk to non-standard firmware.
+    /// Discovery always tests, as that is how boards are recognized.
+    pub fn skip_test(mut self, skip_test: bool) -> Self {
+        self.skip_test = skip_test;
+        self
+    }
+
+    /// Repeats failed requests up to `retries` times after IO errors, see [`RetryPolicy`].
+    ///
+    /// Only reads are retried unless [`B15FBuilder::retry_writes`] is enabled.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retry.retries = retries;
+        self
+    }
+
+    /// How long to wait before each retry, no wait by default.
+    pub fn retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry.backoff = backoff;
+        self
+    }
+
+    /// Retries writes as well. A write whose response got lost already reached the board, so
+    /// retrying executes it twice, only enable this if that is harmless for the experiment.
+    pub fn retry_writes(mut self, writes: bool) -> Self {
+        self.retry.writes = writes;
+        self
+    }
+
+    /// Zeroes the analog outputs and the PWM value when the board is dropped, see [`B15F::set_reset_on_drop`].
+    pub fn reset_on_drop(mut self, reset: bool) -> Self {
+        self.reset_on_drop = reset;
+        self
+    }
+
+    /// The bit order of the digital port values, see [`B15F::set_bit_order`].
+    pub fn bit_order(mut self, order: BitOrder) -> Self {
+        self.bit_order = order;
+        self
+    }
+
+    /// A separate timeout for reading responses, see [`B15F::set_read_timeout`]. Applies to the
+    /// initial test as well.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// A pause between sending a request and reading its response, see [`B15F::set_command_delay`].
+    /// Applies to the initial test as well.
+    pub fn command_delay(mut self, delay: Duration) -> Self {
+        self.command_delay = delay;
+        self
+    }
+
+    /// Whether every request is flushed right after it was written, true by default, see
+    /// [`B15F::set_auto_flush`].
+    pub fn auto_flush(mut self, auto_flush: bool) -> Self {
+        self.auto_flush = auto_flush;
+        self
+    }
+
+    /// Opens the board on the configured port, or discovers it if no port name was set.
+    ///
+    /// # Errors
+    ///
+    /// * If the user may not access the port, the function will return a B15FInitError::PermissionDenied.
+    /// * If the port can't be opened, the function will return a B15FInitError::SerialPortError.
+    /// * If the board fails the test, the function will return a B15FInitError::DeviceNotSupported.
+    /// * If no port name was set and no board was found, the function will return a B15FInitError::DeviceNotFound.
+    pub fn open(self) -> Result<B15F<NativePort>, B15FInitError> {
+        let Some(port_name) = &self.port_name else {
+            return self.discover();
+        };
+        let port = B15F::open_native(port_name, self.baud, self.timeout)?;
+        let mut board = B15F::with_port_unchecked(port);
+        board.set_command_delay(self.command_delay);
+        board.set_auto_flush(self.auto_flush);
+        board.set_read_timeout(self.read_timeout);
+        if !self.skip_test && !board.test()? {
+            return Err(B15FInitError::DeviceNotSupported);
+        }
+        //firmware without the test request most likely lacks the info request as well
+        #[cfg(feature = "experimental")]
+        if !self.skip_test {
+            board.prefetch_board_info();
+        }
+        board.set_retry_policy(self.retry);
+        board.set_reset_on_drop(self.reset_on_drop);
+        board.set_bit_order(self.bit_order);
+        Ok(board)
+    }
+
+    /// Returns the first board found on any port like [`B15F::instance`], using the configured
+    /// baud rate and timeout. The port name is ignored.
+    ///
+    /// # Errors
+    ///
+    /// * If no board was found, the function will return the same errors as [`B15F::try_instance`].
+    pub fn discover(self) -> Result<B15F<NativePort>, B15FInitError> {
+        let mut board = DiscoveredBoards::new(self.baud, self.timeout, port_priority)
+            .retain(maybe_b15f)
+            .try_next()?;
+        #[cfg(feature = "experimental")]
+        board.prefetch_board_info();
+        board.set_command_delay(self.command_delay);
+        board.set_auto_flush(self.auto_flush);
+        board.set_read_timeout(self.read_timeout);
+        board.set_retry_policy(self.retry);
+        board.set_reset_on_drop(self.reset_on_drop);
+        board.set_bit_order(self.bit_order);
+        Ok(board)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{B15FCommandError, B15F};
+use serialport::SerialPort;
+
+/// Offset and gain corrections of the analog inputs, see [`B15F::analog_read_calibrated`].
+///
+/// A channel without an entry reads uncorrected (offset 0, gain 1).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationTable {
+    channels: Vec<(f32, f32)>,
+}
+
+impl CalibrationTable {
+    /// Returns `(offset, gain)` of a channel.
+    pub fn get(&self, channel: u8) -> (f32, f32) {
+        self.channels
+            .get(channel as usize)
+            .copied()
+            .unwrap_or((0.0, 1.0))
+    }
+
+    /// Sets `offset` and `gain` of a channel.
+    ///
+    /// # Errors
+    ///
+    /// * If the offset or the gain is not finite, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set(&mut self, channel: u8, offset: f32, gain: f32) -> Result<(), B15FCommandError> {
+        check_coefficient(offset)?;
+        check_coefficient(gain)?;
+        let index = channel as usize;
+        if self.channels.len() <= index {
+            self.channels.resize(index + 1, (0.0, 1.0));
+        }
+        self.channels[index] = (offset, gain);
+        Ok(())
+    }
+
+    /// Checks the coefficients of every channel, e.g. of a table loaded from a file.
+    pub(crate) fn validate(&self) -> Result<(), B15FCommandError> {
+        for &(offset, gain) in &self.channels {
+            check_coefficient(offset)?;
+            check_coefficient(gain)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the correction of a channel to a raw ADC value, `(raw + offset) * gain`.
+    pub fn apply(&self, channel: u8, raw: u16) -> f32 {
+        let (offset, gain) = self.get(channel);
+        (raw as f32 + offset) * gain
+    }
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Returns the corrections used by [`B15F::analog_read_calibrated`].
+    pub fn calibration(&self) -> &CalibrationTable {
+        &self.calibration
+    }
+
+    /// Replaces all corrections, e.g. with a table loaded from a file.
+    ///
+    /// # Errors
+    ///
+    /// * If a coefficient is not finite, the function will return a B15FCommandError::ValueOutOfRange
+    ///   and keep the current corrections.
+    pub fn set_calibration_table(
+        &mut self,
+        table: CalibrationTable,
+    ) -> Result<(), B15FCommandError> {
+        table.validate()?;
+        self.calibration = table;
+        Ok(())
+    }
+
+    /// Sets the correction of an analog input, see [`CalibrationTable::set`].
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`CalibrationTable::set`].
+    pub fn set_calibration(
+        &mut self,
+        port: u8,
+        offset: f32,
+        gain: f32,
+    ) -> Result<(), B15FCommandError> {
+        self.calibration.set(port, offset, gain)
+    }
+
+    /// Computes the correction of an analog input from two reference measurements.
+    ///
+    /// Each point is the raw value [`B15F::analog_read`] returned for a known input and the value
+    /// (e.g. volts) the calibrated read should return for it. Points far apart give the most
+    /// accurate gain, e.g. ground and a reference close to full scale.
+    ///
+    /// # Errors
+    ///
+    /// * If both raw values or both reference values are equal, or a reference value is not finite,
+    ///   the coefficients aren't finite and the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn calibrate_channel(
+        &mut self,
+        port: u8,
+        low: (u16, f32),
+        high: (u16, f32),
+    ) -> Result<(), B15FCommandError> {
+        let (raw_low, value_low) = low;
+        let (raw_high, value_high) = high;
+        //equal raw values give an infinite gain, equal reference values an infinite offset
+        let gain = (value_high - value_low) / (raw_high as f32 - raw_low as f32);
+        let offset = value_low / gain - raw_low as f32;
+        self.calibration.set(port, offset, gain)
+    }
+
+    /// Reads an analog input and applies its correction, `(raw + offset) * gain`.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::analog_read`].
+    pub fn analog_read_calibrated(&mut self, port: u8) -> Result<f32, B15FCommandError> {
+        let raw = self.analog_read(port)?;
+        Ok(self.calibration.apply(port, raw))
+    }
+}
+
+fn check_coefficient(value: f32) -> Result<(), B15FCommandError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(B15FCommandError::ValueOutOfRange {
+            value: value.into(),
+            min: f32::MIN.into(),
+            max: f32::MAX.into(),
+        })
+    }
+}
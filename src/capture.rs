@@ -0,0 +1,148 @@
+use crate::{check_range, B15FCommandError, Port, B15F};
+use serialport::SerialPort;
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// A signal transition to trigger on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Edge {
+    /// Low to high.
+    Rising,
+    /// High to low.
+    Falling,
+    /// Either direction.
+    Any,
+}
+
+impl Edge {
+    /// Returns whether the transition from `before` to `after` (`true` = high) matches this edge.
+    pub fn matches(self, before: bool, after: bool) -> bool {
+        match self {
+            Edge::Rising => !before && after,
+            Edge::Falling => before && !after,
+            Edge::Any => before != after,
+        }
+    }
+}
+
+/// Samples around a trigger event, see [`B15F::capture_triggered`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Capture {
+    /// The pre-trigger samples, the trigger sample and the post-trigger samples in order.
+    pub samples: Vec<u16>,
+    /// The index of the sample which fired the trigger.
+    pub trigger_index: usize,
+}
+
+impl Capture {
+    /// The samples taken before the trigger fired.
+    pub fn pre_trigger(&self) -> &[u16] {
+        &self.samples[..self.trigger_index]
+    }
+
+    /// The trigger sample and everything after it.
+    pub fn post_trigger(&self) -> &[u16] {
+        &self.samples[self.trigger_index..]
+    }
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Waits until a bit (0 - 7) of a digital port transitions in the direction of `edge`.
+    ///
+    /// The board can't notify the host over serial, so the port is polled with
+    /// [`B15F::digital_read`] as fast as the round trip allows. Pulses shorter than a round trip
+    /// may be missed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the edge was seen, `Ok(false)` if `timeout` elapsed first.
+    ///
+    /// # Errors
+    ///
+    /// * If the bit is greater than 7, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * Same as [`B15F::digital_read`].
+    pub fn wait_for_edge(
+        &mut self,
+        port: Port,
+        bit: u8,
+        edge: Edge,
+        timeout: Duration,
+    ) -> Result<bool, B15FCommandError> {
+        check_range(bit as u16, 7)?;
+        //a timeout too large for an Instant (e.g. Duration::MAX) waits forever
+        let deadline = Instant::now().checked_add(timeout);
+        let mut previous = self.digital_read(port)? & (1 << bit) != 0;
+        while deadline.is_none_or(|deadline| Instant::now() < deadline) {
+            let current = self.digital_read(port)? & (1 << bit) != 0;
+            if edge.matches(previous, current) {
+                return Ok(true);
+            }
+            previous = current;
+        }
+        Ok(false)
+    }
+
+    /// Captures an analog input around a trigger event, like a storage oscilloscope.
+    ///
+    /// The channel is sampled continuously while the last `pre_samples` samples are kept in a ring
+    /// buffer. Once two consecutive samples cross `threshold` in the direction of `edge` (a sample equal
+    /// to the threshold counts as high), `post_samples` further samples are taken and returned together
+    /// with the buffered ones. If the trigger fires before the ring buffer is full, fewer pre-trigger
+    /// samples are returned.
+    ///
+    /// Samples are taken with single `analog_read` requests, so the sample rate is limited by the
+    /// serial round trip. This blocks until the trigger fires or `timeout` elapsed, the post-trigger
+    /// samples are taken regardless of the timeout.
+    ///
+    /// # Errors
+    ///
+    /// * If the trigger didn't fire within `timeout`, the function will return a B15FCommandError::Timeout.
+    /// * Same as [`B15F::analog_read`], the capture is aborted on the first error.
+    pub fn capture_triggered(
+        &mut self,
+        channel: u8,
+        threshold: u16,
+        edge: Edge,
+        pre_samples: usize,
+        post_samples: usize,
+        timeout: Duration,
+    ) -> Result<Capture, B15FCommandError> {
+        //a timeout too large for an Instant (e.g. Duration::MAX) waits forever
+        let deadline = Instant::now().checked_add(timeout);
+        let mut ring = VecDeque::with_capacity(pre_samples + 1);
+        let mut previous = self.analog_read(channel)?;
+        let trigger = loop {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(B15FCommandError::Timeout(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("trigger did not fire within {:?}", timeout),
+                )));
+            }
+            ring.push_back(previous);
+            if ring.len() > pre_samples {
+                ring.pop_front();
+            }
+            let sample = self.analog_read(channel)?;
+            if edge.matches(previous >= threshold, sample >= threshold) {
+                break sample;
+            }
+            previous = sample;
+        };
+
+        let trigger_index = ring.len();
+        let mut samples = Vec::with_capacity(trigger_index + 1 + post_samples);
+        samples.extend(ring);
+        samples.push(trigger);
+        for _ in 0..post_samples {
+            samples.push(self.analog_read(channel)?);
+        }
+        Ok(Capture {
+            samples,
+            trigger_index,
+        })
+    }
+}
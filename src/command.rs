@@ -0,0 +1,307 @@
+use crate::{
+    check_range, proto, pwm_max_frequency, pwm_min_frequency, B15FCommandError, OutputConfig, Port,
+    B15F, RQ_READ_DIP_SWITCH, RQ_SELF_TEST, RQ_SERVO_DISABLE, RQ_SERVO_ENABLE, SERVO_MAX_PULSE,
+};
+use serialport::SerialPort;
+use std::time::Instant;
+
+/// A single board operation as data, executed with [`B15F::execute`].
+///
+/// Useful to store, log or replay command sequences and to drive a board from a uniform path
+/// (e.g. a REPL or a worker thread) instead of calling the typed methods directly.
+///
+/// Every request with a fixed-size answer has a variant and the typed methods are thin wrappers
+/// executing their variant. Left out are the board info and the ADC/DAC stroke, whose answers
+/// have a variable length (use [`B15F::board_info`] and `B15F::adc_dac_stroke`).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    Test,
+    SelfTest,
+    IntegrationTest,
+    ReadDipSwitch,
+    DigitalWrite {
+        port: Port,
+        value: u8,
+    },
+    DigitalRead {
+        port: Port,
+    },
+    DigitalReadRaw {
+        port: Port,
+    },
+    AnalogWrite {
+        port: Port,
+        value: u16,
+    },
+    AnalogWriteAll {
+        value0: u16,
+        value1: u16,
+    },
+    AnalogRead {
+        channel: u8,
+    },
+    ConfigureOutputs(OutputConfig),
+    PwmSetFrequency {
+        frequency: f32,
+    },
+    PwmSetValue {
+        value: u8,
+    },
+    PwmDisable,
+    ServoEnable,
+    ServoDisable,
+    ServoSetPosition {
+        pulse: u16,
+    },
+    SetCounterOffset {
+        offset: u16,
+    },
+    #[cfg(feature = "memory-unsafe")]
+    Peek8 {
+        address: u16,
+    },
+    #[cfg(feature = "memory-unsafe")]
+    Poke8 {
+        address: u16,
+        value: u8,
+    },
+    #[cfg(feature = "memory-unsafe")]
+    Peek16 {
+        address: u16,
+    },
+    #[cfg(feature = "memory-unsafe")]
+    Poke16 {
+        address: u16,
+        value: u16,
+    },
+}
+
+/// The answer to an executed [`Command`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CommandResult {
+    /// The command has no result besides succeeding.
+    Done,
+    /// The result of [`Command::Test`], [`Command::SelfTest`] or [`Command::IntegrationTest`].
+    Test(bool),
+    /// The byte read by [`Command::DigitalRead`] or [`Command::DigitalReadRaw`].
+    Digital(u8),
+    /// The value read by [`Command::AnalogRead`].
+    Analog(u16),
+    /// The timer top value answered to [`Command::PwmSetFrequency`].
+    PwmTop(u8),
+    /// The positions read by [`Command::ReadDipSwitch`].
+    DipSwitch(u8),
+    /// The byte read by `Command::Peek8`.
+    #[cfg(feature = "memory-unsafe")]
+    Memory8(u8),
+    /// The word read by `Command::Peek16`.
+    #[cfg(feature = "memory-unsafe")]
+    Memory16(u16),
+}
+
+/// Executes a command and unwraps the [`CommandResult`] variant the typed method returns.
+macro_rules! execute_as {
+    ($board:expr, $command:expr, $result:path) => {
+        match $board.execute($command)? {
+            $result(value) => Ok(value),
+            other => unreachable!("unexpected result {:?}", other),
+        }
+    };
+}
+
+pub(crate) use execute_as;
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Executes a [`Command`], the typed methods all end up here.
+    ///
+    /// # Errors
+    ///
+    /// * Same as the typed method of the command.
+    pub fn execute(&mut self, command: Command) -> Result<CommandResult, B15FCommandError> {
+        let result = match command {
+            Command::Test => CommandResult::Test(self.test_detailed()?.ok),
+            Command::SelfTest => CommandResult::Test(self.with_retries(false, |board| {
+                let mut response = [0u8];
+                board.transact(&[RQ_SELF_TEST], &mut response)?;
+                proto::decode_self_test(&response)
+            })?),
+            Command::IntegrationTest => CommandResult::Test(self.with_retries(true, |board| {
+                let value = rand::random::<u16>();
+                let mut response = [0u8; 2];
+                board.transact(&proto::encode_int_test(value), &mut response)?;
+                proto::decode_int_test(&response, value)
+            })?),
+            Command::ReadDipSwitch => {
+                let value = self.with_retries(true, |board| {
+                    let mut response = [0u8];
+                    board.transact(&[RQ_READ_DIP_SWITCH], &mut response)?;
+                    proto::decode_dip_switch(&response)
+                })?;
+                self.dip_switch = Some(value);
+                CommandResult::DipSwitch(value)
+            }
+            Command::DigitalWrite { port, value } => {
+                self.command_ok(&proto::encode_digital_write(
+                    port,
+                    self.bit_order.apply(value),
+                ))?;
+                self.digital_outputs[port.index()] = Some(value);
+                CommandResult::Done
+            }
+            Command::DigitalRead { port } => {
+                CommandResult::Digital(self.with_retries(true, |board| {
+                    let started = Instant::now();
+                    let result = board
+                        .send_digital_read_request(port)
+                        .and_then(|_| board.read_digital_response());
+                    board.metrics.record(started.elapsed(), &result);
+                    result
+                })?)
+            }
+            Command::DigitalReadRaw { port } => {
+                CommandResult::Digital(self.with_retries(true, |board| {
+                    let started = Instant::now();
+                    let result = board
+                        .send_digital_read_request(port)
+                        .and_then(|_| board.read_raw_digital_response());
+                    board.metrics.record(started.elapsed(), &result);
+                    result
+                })?)
+            }
+            Command::AnalogWrite { port, value } => {
+                check_range(value, 1023)?;
+                self.command_ok(&proto::encode_analog_write(port, value))?;
+                CommandResult::Done
+            }
+            Command::AnalogWriteAll { value0, value1 } => {
+                check_range(value0, 1023)?;
+                check_range(value1, 1023)?;
+                let data = [
+                    proto::encode_analog_write(Port::Port0, value0),
+                    proto::encode_analog_write(Port::Port1, value1),
+                ]
+                .concat();
+                self.with_retries(false, |board| {
+                    let mut response = [0u8; 2];
+                    board.transact(&data, &mut response)?;
+                    proto::decode_statuses(&response)
+                })?;
+                CommandResult::Done
+            }
+            Command::AnalogRead { channel } => {
+                CommandResult::Analog(self.with_retries(true, |board| {
+                    let started = Instant::now();
+                    let result = board
+                        .send_analog_read_request(channel)
+                        .and_then(|_| board.read_analog_response());
+                    board.metrics.record(started.elapsed(), &result);
+                    result
+                })?)
+            }
+            Command::ConfigureOutputs(config) => {
+                for value in config.analog {
+                    check_range(value, 1023)?;
+                }
+                let [digital0, digital1] = config.digital.map(|value| self.bit_order.apply(value));
+                let data = [
+                    &proto::encode_digital_write(Port::Port0, digital0)[..],
+                    &proto::encode_digital_write(Port::Port1, digital1),
+                    &proto::encode_analog_write(Port::Port0, config.analog[0]),
+                    &proto::encode_analog_write(Port::Port1, config.analog[1]),
+                ]
+                .concat();
+                self.with_retries(false, |board| {
+                    let mut response = [0u8; 4];
+                    board.transact(&data, &mut response)?;
+                    proto::decode_statuses(&response)
+                })?;
+                self.digital_outputs = config.digital.map(Some);
+                CommandResult::Done
+            }
+            Command::PwmSetFrequency { frequency } => {
+                let hz = frequency.round();
+                let (min, max) = (pwm_min_frequency(), pwm_max_frequency());
+                if hz != 0.0 && !(hz >= min as f32 && hz <= max as f32) {
+                    return Err(B15FCommandError::ValueOutOfRange {
+                        value: hz.into(),
+                        min: min.into(),
+                        max: max.into(),
+                    });
+                }
+                let top = self.with_retries(false, |board| {
+                    let mut response = [0u8];
+                    board.transact(&proto::encode_pwm_frequency(hz as u32), &mut response)?;
+                    proto::decode_pwm_top(&response)
+                })?;
+                self.pwm.frequency = Some(frequency);
+                self.pwm.top = Some(top);
+                CommandResult::PwmTop(top)
+            }
+            Command::PwmSetValue { value } => {
+                self.command_ok(&proto::encode_pwm_value(value))?;
+                self.pwm.value = Some(value);
+                CommandResult::Done
+            }
+            Command::PwmDisable => {
+                self.execute(Command::PwmSetFrequency { frequency: 0.0 })?;
+                CommandResult::Done
+            }
+            Command::ServoEnable => {
+                self.command_ok(&[RQ_SERVO_ENABLE])?;
+                CommandResult::Done
+            }
+            Command::ServoDisable => {
+                self.command_ok(&[RQ_SERVO_DISABLE])?;
+                CommandResult::Done
+            }
+            Command::ServoSetPosition { pulse } => {
+                check_range(pulse, SERVO_MAX_PULSE)?;
+                self.command_ok(&proto::encode_servo_position(pulse))?;
+                CommandResult::Done
+            }
+            Command::SetCounterOffset { offset } => {
+                self.command_ok(&proto::encode_counter_offset(offset))?;
+                CommandResult::Done
+            }
+            #[cfg(feature = "memory-unsafe")]
+            Command::Peek8 { address } => {
+                //reading an I/O register may have side effects, so this counts as a write
+                CommandResult::Memory8(self.with_retries(false, |board| {
+                    let mut response = [0u8];
+                    board.transact(&proto::encode_get_mem_8(address), &mut response)?;
+                    Ok(response[0])
+                })?)
+            }
+            #[cfg(feature = "memory-unsafe")]
+            Command::Poke8 { address, value } => {
+                self.with_retries(false, |board| {
+                    let mut response = [0u8];
+                    board.transact(&proto::encode_set_mem_8(address, value), &mut response)?;
+                    proto::decode_echo(&response, &[value])
+                })?;
+                CommandResult::Done
+            }
+            #[cfg(feature = "memory-unsafe")]
+            Command::Peek16 { address } => {
+                CommandResult::Memory16(self.with_retries(false, |board| {
+                    let mut response = [0u8; 2];
+                    board.transact(&proto::encode_get_mem_16(address), &mut response)?;
+                    Ok(u16::from_le_bytes(response))
+                })?)
+            }
+            #[cfg(feature = "memory-unsafe")]
+            Command::Poke16 { address, value } => {
+                self.with_retries(false, |board| {
+                    let mut response = [0u8; 2];
+                    board.transact(&proto::encode_set_mem_16(address, value), &mut response)?;
+                    proto::decode_echo(&response, &value.to_le_bytes())
+                })?;
+                CommandResult::Done
+            }
+        };
+        Ok(result)
+    }
+}
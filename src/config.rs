@@ -0,0 +1,118 @@
+use crate::{
+    check_analog_channels, check_analog_reference, B15FCommandError, CalibrationTable, OutputCurve,
+    B15F,
+};
+use serialport::SerialPort;
+#[cfg(feature = "serde")]
+use std::io;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
+//Version written to new config files, files with a newer version are rejected
+const CONFIG_VERSION: u32 = 1;
+
+/// The host-side configuration of a board (not the state of the device itself).
+///
+/// Fields added in later versions have defaults, so older files keep loading.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoardConfig {
+    /// The format version of the file.
+    pub version: u32,
+    /// The curves of analog output 0 and 1, see [`B15F::set_output_curve`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub output_curves: [OutputCurve; 2],
+    /// See [`B15F::set_analog_channels`].
+    #[cfg_attr(feature = "serde", serde(default = "default_analog_channels"))]
+    pub analog_channels: u8,
+    /// See [`B15F::set_analog_reference`].
+    #[cfg_attr(feature = "serde", serde(default = "default_analog_reference"))]
+    pub analog_reference: f32,
+    /// The corrections of the analog inputs, see [`B15F::set_calibration_table`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub calibration: CalibrationTable,
+}
+
+#[cfg(feature = "serde")]
+fn default_analog_channels() -> u8 {
+    crate::DEFAULT_ANALOG_CHANNELS
+}
+
+#[cfg(feature = "serde")]
+fn default_analog_reference() -> f32 {
+    crate::DEFAULT_ANALOG_REFERENCE
+}
+
+impl BoardConfig {
+    fn validate(&self) -> Result<(), B15FCommandError> {
+        //files written by a newer version may contain settings this version would drop
+        if self.version > CONFIG_VERSION {
+            return Err(B15FCommandError::ValueOutOfRange {
+                value: self.version.into(),
+                min: 0.0,
+                max: CONFIG_VERSION.into(),
+            });
+        }
+        check_analog_channels(self.analog_channels)?;
+        check_analog_reference(self.analog_reference)?;
+        for curve in &self.output_curves {
+            curve.validate()?;
+        }
+        self.calibration.validate()
+    }
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Returns the current host-side configuration.
+    pub fn config(&self) -> BoardConfig {
+        BoardConfig {
+            version: CONFIG_VERSION,
+            output_curves: self.output_curves.clone(),
+            analog_channels: self.analog_channels,
+            analog_reference: self.analog_reference,
+            calibration: self.calibration.clone(),
+        }
+    }
+
+    /// Applies a configuration, e.g. one taken from another board.
+    ///
+    /// The configuration is validated first, an invalid one leaves the current configuration untouched.
+    ///
+    /// # Errors
+    ///
+    /// * If the configuration is newer than this version of the crate, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If there are no analog channels or the reference voltage is not finite and positive, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If an output curve is invalid, the function will return the same errors as [`B15F::set_output_curve`].
+    /// * If a calibration coefficient is not finite, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn apply_config(&mut self, config: BoardConfig) -> Result<(), B15FCommandError> {
+        config.validate()?;
+        self.output_curves = config.output_curves;
+        self.analog_channels = config.analog_channels;
+        self.analog_reference = config.analog_reference;
+        self.calibration = config.calibration;
+        Ok(())
+    }
+
+    /// Saves the host-side configuration as JSON, see [`B15F::config`].
+    #[cfg(feature = "serde")]
+    pub fn save_config(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(io::BufWriter::new(file), &self.config())?;
+        Ok(())
+    }
+
+    /// Loads and applies a configuration saved with [`B15F::save_config`].
+    ///
+    /// Invalid files or files written by a newer version are rejected with
+    /// [`io::ErrorKind::InvalidData`] and leave the current configuration untouched.
+    #[cfg(feature = "serde")]
+    pub fn load_config(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = std::fs::File::open(path)?;
+        let config: BoardConfig = serde_json::from_reader(io::BufReader::new(file))?;
+        self.apply_config(config)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
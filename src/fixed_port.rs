@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::io;
+
+/// Implements [`std::io::Read`] (with [`read_output`], unless no `silent_error` is given) and
+/// [`serialport::SerialPort`] for an in-memory port with fixed settings.
+///
+/// The type needs a `timeout: Duration` field and an `output()` method returning the guarded
+/// buffer of readable bytes. Settings are accepted and ignored, the control lines look like a
+/// connected board.
+macro_rules! fixed_serial_port {
+    ($port:ty, $name:literal, $clone_error:literal, $silent_error:literal) => {
+        impl std::io::Read for $port {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                crate::fixed_port::read_output(&mut self.output(), buf, $silent_error)
+            }
+        }
+
+        fixed_serial_port!($port, $name, $clone_error);
+    };
+    ($port:ty, $name:literal, $clone_error:literal) => {
+        impl serialport::SerialPort for $port {
+            fn name(&self) -> Option<String> {
+                Some($name.to_string())
+            }
+
+            fn baud_rate(&self) -> serialport::Result<u32> {
+                Ok(crate::BAUD)
+            }
+
+            fn data_bits(&self) -> serialport::Result<serialport::DataBits> {
+                Ok(serialport::DataBits::Eight)
+            }
+
+            fn flow_control(&self) -> serialport::Result<serialport::FlowControl> {
+                Ok(serialport::FlowControl::None)
+            }
+
+            fn parity(&self) -> serialport::Result<serialport::Parity> {
+                Ok(serialport::Parity::None)
+            }
+
+            fn stop_bits(&self) -> serialport::Result<serialport::StopBits> {
+                Ok(serialport::StopBits::One)
+            }
+
+            fn timeout(&self) -> std::time::Duration {
+                self.timeout
+            }
+
+            fn set_baud_rate(&mut self, _baud_rate: u32) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn set_data_bits(
+                &mut self,
+                _data_bits: serialport::DataBits,
+            ) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn set_flow_control(
+                &mut self,
+                _flow_control: serialport::FlowControl,
+            ) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn set_parity(&mut self, _parity: serialport::Parity) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn set_stop_bits(
+                &mut self,
+                _stop_bits: serialport::StopBits,
+            ) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn set_timeout(&mut self, timeout: std::time::Duration) -> serialport::Result<()> {
+                self.timeout = timeout;
+                Ok(())
+            }
+
+            fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+                Ok(true)
+            }
+
+            fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+                Ok(true)
+            }
+
+            fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+                Ok(false)
+            }
+
+            fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+                Ok(true)
+            }
+
+            fn bytes_to_read(&self) -> serialport::Result<u32> {
+                Ok(self.output().len() as u32)
+            }
+
+            fn bytes_to_write(&self) -> serialport::Result<u32> {
+                Ok(0)
+            }
+
+            fn clear(&self, buffer_to_clear: serialport::ClearBuffer) -> serialport::Result<()> {
+                if let serialport::ClearBuffer::Input | serialport::ClearBuffer::All =
+                    buffer_to_clear
+                {
+                    self.output().clear();
+                }
+                Ok(())
+            }
+
+            fn try_clone(&self) -> serialport::Result<Box<dyn serialport::SerialPort>> {
+                Err(serialport::Error::new(
+                    serialport::ErrorKind::Unknown,
+                    $clone_error,
+                ))
+            }
+
+            fn set_break(&self) -> serialport::Result<()> {
+                Ok(())
+            }
+
+            fn clear_break(&self) -> serialport::Result<()> {
+                Ok(())
+            }
+        }
+    };
+}
+
+pub(crate) use fixed_serial_port;
+
+/// Reads from the buffer of an in-memory port, an empty buffer times out with `silent_error`.
+pub(crate) fn read_output(
+    output: &mut VecDeque<u8>,
+    buf: &mut [u8],
+    silent_error: &str,
+) -> io::Result<usize> {
+    if output.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, silent_error));
+    }
+    let len = buf.len().min(output.len());
+    for (target, byte) in buf.iter_mut().zip(output.drain(..len)) {
+        *target = byte;
+    }
+    Ok(len)
+}
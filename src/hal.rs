@@ -0,0 +1,116 @@
+use crate::{check_range, B15FCommandError, Port, B15F};
+use embedded_hal::digital::{Error, ErrorKind, ErrorType, InputPin, OutputPin, StatefulOutputPin};
+use serialport::SerialPort;
+
+/// A single bit of a digital port implementing the `embedded-hal` digital pin traits, see [`B15F::pin`].
+///
+/// Reading uses the digital input of the port, writing the digital output, which are separate
+/// pins on the board. Setting a pin is a read-modify-write of the whole output byte like
+/// [`B15F::digital_set_bit_cached`]: the other bits are taken from the last value written through
+/// this board handle, or read with [`B15F::digital_read`] if the port wasn't written yet.
+///
+/// Toggling two pins of the same port is two read-modify-writes of the same byte. If the port is
+/// written by anything else in between (another process, another handle or a board reset), the
+/// second write sends the bits it knew before and reverts the other change. A pin borrows the board
+/// mutably, so pins of one handle are toggled one after another and always see each other's bits.
+pub struct B15FPin<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+    port: Port,
+    bit: u8,
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Returns bit `bit` (0 - 7) of a digital port as an `embedded-hal` pin, so the board can be
+    /// used as a GPIO expander by driver crates.
+    ///
+    /// # Errors
+    ///
+    /// * If the bit is greater than 7, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn pin(&mut self, port: Port, bit: u8) -> Result<B15FPin<'_, P>, B15FCommandError> {
+        check_range(bit as u16, 7)?;
+        Ok(B15FPin {
+            board: self,
+            port,
+            bit,
+        })
+    }
+}
+
+impl<P> B15FPin<'_, P>
+where
+    P: SerialPort,
+{
+    fn mask(&self) -> u8 {
+        1 << self.bit
+    }
+
+    fn write(&mut self, high: bool) -> Result<(), B15FCommandError> {
+        self.board.digital_set_bit_cached(self.port, self.bit, high)
+    }
+
+    fn output(&mut self) -> Result<bool, B15FCommandError> {
+        let current = match self.board.last_written(self.port) {
+            Some(current) => current,
+            None => self.board.digital_read(self.port)?,
+        };
+        Ok(current & self.mask() != 0)
+    }
+}
+
+impl Error for B15FCommandError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+impl<P> ErrorType for B15FPin<'_, P>
+where
+    P: SerialPort,
+{
+    type Error = B15FCommandError;
+}
+
+impl<P> OutputPin for B15FPin<'_, P>
+where
+    P: SerialPort,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.write(false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.write(true)
+    }
+}
+
+impl<P> StatefulOutputPin for B15FPin<'_, P>
+where
+    P: SerialPort,
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.output()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.output()?)
+    }
+}
+
+impl<P> InputPin for B15FPin<'_, P>
+where
+    P: SerialPort,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.board.digital_read(self.port)? & self.mask() != 0)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high()?)
+    }
+}
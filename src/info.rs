@@ -0,0 +1,99 @@
+use crate::{proto, B15FCommandError, B15F, RQ_INFO};
+use serialport::SerialPort;
+use std::time::Instant;
+
+/// Firmware information reported by the board, see [`B15F::board_info`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoardInfo {
+    /// The firmware revision, empty if the board didn't report one.
+    pub firmware: String,
+    /// When the firmware was built, as reported by the compiler (e.g. `Jan  1 1970 00:00:00`).
+    pub build_date: Option<String>,
+}
+
+impl BoardInfo {
+    /// Parses the firmware revision as `major.minor.patch`, e.g. `1.2` or `v1.2.3`.
+    ///
+    /// Missing parts count as 0, text after the numbers is ignored. Returns `None` if the revision
+    /// doesn't start with a number.
+    pub fn version(&self) -> Option<(u32, u32, u32)> {
+        let firmware = self.firmware.trim();
+        let firmware = firmware.strip_prefix(['v', 'V']).unwrap_or(firmware);
+        let end = firmware
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(firmware.len());
+        let mut parts = firmware[..end].split('.').map(str::parse::<u32>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().and_then(Result::ok).unwrap_or(0);
+        let patch = parts.next().and_then(Result::ok).unwrap_or(0);
+        Some((major, minor, patch))
+    }
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Reads which firmware the board is running.
+    ///
+    /// The board answers with a count followed by that many length-prefixed strings: build date,
+    /// build time and firmware revision. Missing or empty strings result in an empty `firmware`
+    /// or no `build_date` instead of an error.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If a string is not ASCII or the response doesn't end with MSG_OK, the function will return a B15FCommandError::B15FError.
+    pub fn board_info(&mut self) -> Result<BoardInfo, B15FCommandError> {
+        let result = self.with_retries(true, |board| {
+            let started = Instant::now();
+            let result = board
+                .write_request(&[RQ_INFO])
+                .and_then(|_| board.read_info_response());
+            board.metrics.record(started.elapsed(), &result);
+            result
+        });
+        if let Ok(info) = &result {
+            self.board_info = Some(info.clone());
+        }
+        result
+    }
+
+    /// Reads the board info while opening, so the version checks of the experimental requests
+    /// don't add a round trip to the first call. A failure is ignored, the info is then read
+    /// again on first use.
+    #[cfg(feature = "experimental")]
+    pub(crate) fn prefetch_board_info(&mut self) {
+        if self.board_info().is_err() {
+            let _ = self.flush_input();
+        }
+    }
+
+    /// Returns the board info read by the last [`B15F::board_info`] call, reading it if there was none.
+    #[cfg(feature = "experimental")]
+    pub(crate) fn cached_board_info(&mut self) -> Result<&BoardInfo, B15FCommandError> {
+        if self.board_info.is_none() {
+            self.board_info()?;
+        }
+        self.board_info.as_ref().ok_or(B15FCommandError::B15FError)
+    }
+
+    /// Reads the variable-length info response and decodes it with [`proto::decode_info`].
+    fn read_info_response(&mut self) -> Result<BoardInfo, B15FCommandError> {
+        let mut response = vec![0u8];
+        self.read_response(&mut response)?;
+        for _ in 0..response[0] {
+            let mut len = [0u8];
+            self.read_response(&mut len)?;
+            let start = response.len();
+            response.push(len[0]);
+            response.resize(start + 1 + len[0] as usize, 0);
+            self.read_response(&mut response[start + 1..])?;
+        }
+        let mut status = [0u8];
+        self.read_response(&mut status)?;
+        response.push(status[0]);
+        proto::decode_info(&response)
+    }
+}
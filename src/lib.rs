@@ -1,61 +1,365 @@
 #[cfg(feature = "experimental")]
 use bitflags::bitflags;
+use command::execute_as;
 #[cfg(feature = "log")]
-use log::debug;
+use log::{debug, trace};
 use rand::random;
+use serialport::ClearBuffer;
 use serialport::{SerialPortType};
 #[cfg(windows)]
 use serialport::{COMPort};
 #[cfg(not(windows))]
 use serialport::TTYPort;
-use std::time::Duration;
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod asynchronous;
+mod builder;
+mod calibration;
+mod capture;
+mod command;
+mod config;
+#[cfg(any(test, feature = "record", feature = "sim", feature = "testing"))]
+mod fixed_port;
+#[cfg(feature = "embedded-hal")]
+mod hal;
+mod info;
+#[cfg(feature = "memory-unsafe")]
+mod memory;
+mod metrics;
+mod orchestrator;
+pub mod proto;
+mod raw;
+#[cfg(feature = "record")]
+pub mod record;
+mod shared;
+#[cfg(feature = "sim")]
+pub mod sim;
+mod stream;
+#[cfg(feature = "async")]
+mod supervisor;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod timing;
+mod typestate;
+
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncB15F;
+pub use builder::B15FBuilder;
+pub use calibration::CalibrationTable;
+pub use capture::{Capture, Edge};
+pub use command::{Command, CommandResult};
+pub use config::BoardConfig;
+#[cfg(feature = "embedded-hal")]
+pub use hal::B15FPin;
+pub use info::BoardInfo;
+pub use metrics::{LatencyHistogram, Metrics};
+pub use orchestrator::{BoardId, Orchestrator, OrchestratorError};
+pub use raw::RawIo;
+pub use shared::SharedB15F;
+pub use stream::AnalogStream;
+#[cfg(feature = "async")]
+pub use supervisor::{ConnectionState, SupervisedB15F, SupervisorConfig, WhileDisconnected};
+pub use timing::{LatencyStats, Timestamped};
+pub use typestate::{PwmConfigured, PwmUnconfigured, ServoDisabled, ServoEnabled};
+
 #[cfg(windows)]
 pub type NativePort = COMPort;
 #[cfg(not(windows))]
 pub type NativePort = TTYPort;
 
+/// USB vendor and product ids of the serial adapters found on B15F boards (FTDI FT232R, CH340
+/// and CP210x). Discovery skips USB ports with other ids without writing to them.
+pub const KNOWN_USB_IDS: &[(u16, u16)] = &[(0x0403, 0x6001), (0x1A86, 0x7523), (0x10C4, 0xEA60)];
+
 //Serial port settings
 const BAUD: u32 = 57600;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(5000);
+//Boards answer the test request within a few milliseconds, don't wait long on other devices
+const DISCOVERY_TIMEOUT: Duration = Duration::from_millis(300);
+
+//Timeout while probing for unknown commands
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+//DTR pulse resetting the board and how long its bootloader waits before starting the firmware
+const RESET_PULSE: Duration = Duration::from_millis(100);
+const BOOTLOADER_DELAY: Duration = Duration::from_millis(2000);
+
+//Number of analog inputs on the standard board
+const DEFAULT_ANALOG_CHANNELS: u8 = 8;
+//ADC reference voltage of the standard board
+const DEFAULT_ANALOG_REFERENCE: f32 = 5.0;
+//ADC counts a checked reading may deviate from the median, about 3% of the range
+const ANALOG_OUTLIER_THRESHOLD: u16 = 32;
+
+//Size of the OS input buffer (Linux tty and Windows default), more pending bytes are dropped
+#[cfg(feature = "experimental")]
+const INPUT_BUFFER_SIZE: u32 = 4096;
+//Warn when the input buffer is filled beyond this while reading
+#[cfg(feature = "experimental")]
+const INPUT_BUFFER_WARN: u32 = INPUT_BUFFER_SIZE * 3 / 4;
 
 const MSG_OK: u8 = 0xFF;
-//const MSG_ERROR: u8 = 0xFE;
+const MSG_ERROR: u8 = 0xFE;
 //const MAX_DATA_SIZE: u8 = 64;
 
 //Requests
-//const RQ_DISCARD: u8 = 0;
+#[cfg(feature = "sim")]
+const RQ_DISCARD: u8 = 0;
 const RQ_TEST: u8 = 1;
-//const RQ_INFO: u8 = 2;
-//const RQ_INT_TEST: u8 = 3;
-//const RQ_SELF_TEST: u8 = 4;
+const RQ_INFO: u8 = 2;
+const RQ_INT_TEST: u8 = 3;
+const RQ_SELF_TEST: u8 = 4;
 const RQ_DIGITAL_WRITE_0: u8 = 5;
 const RQ_DIGITAL_WRITE_1: u8 = 6;
 const RQ_DIGITAL_READ_0: u8 = 7;
 const RQ_DIGITAL_READ_1: u8 = 8;
-//const RQ_READ_DIP_SWITCH: u8 = 9;
+const RQ_READ_DIP_SWITCH: u8 = 9;
 const RQ_ANALOG_WRITE_0: u8 = 10;
 const RQ_ANALOG_WRITE_1: u8 = 11;
 const RQ_ANALOG_READ: u8 = 12;
-//const RQ_ADC_DAC_STROKE: u8 = 13;
+#[cfg(any(feature = "experimental", feature = "sim"))]
+const RQ_ADC_DAC_STROKE: u8 = 13;
 const RQ_PWM_SET_FREQ: u8 = 14;
 const RQ_PWM_SET_VALUE: u8 = 15;
-//NO NO NO!!!
-//const RQ_SET_MEM_8: u8 = 16;
-//const RQ_GET_MEM_8: u8 = 17;
-//const RQ_SET_MEM_16: u8 = 18;
-//const RQ_GET_MEM_16: u8 = 19;
-//const RQ_COUNTER_OFFSET: u8 = 20;
-//const RQ_SERVO_ENABLE: u8 = 21;
-//const RQ_SERVO_DISABLE: u8 = 22;
-//const RQ_SERVO_SET_POS: u8 = 23;
-
-#[derive(Debug, Copy, Clone)]
+//NO NO NO!!! (unless you really know what you are doing)
+#[cfg(any(feature = "memory-unsafe", feature = "sim"))]
+const RQ_SET_MEM_8: u8 = 16;
+#[cfg(any(feature = "memory-unsafe", feature = "sim"))]
+const RQ_GET_MEM_8: u8 = 17;
+#[cfg(any(feature = "memory-unsafe", feature = "sim"))]
+const RQ_SET_MEM_16: u8 = 18;
+#[cfg(any(feature = "memory-unsafe", feature = "sim"))]
+const RQ_GET_MEM_16: u8 = 19;
+const RQ_COUNTER_OFFSET: u8 = 20;
+const RQ_SERVO_ENABLE: u8 = 21;
+const RQ_SERVO_DISABLE: u8 = 22;
+const RQ_SERVO_SET_POS: u8 = 23;
+
+//Longest servo pulse accepted by the firmware in microseconds
+const SERVO_MAX_PULSE: u16 = 19000;
+
+//Clock of the board's microcontroller and the prescalers of the PWM timer, in the order the firmware tries them
+const CPU_FREQUENCY: u32 = 20_000_000;
+const PWM_PRESCALERS: [u32; 5] = [1, 8, 64, 256, 1024];
+
+/// Lowest PWM frequency in Hz, at which the slowest prescaler still fits the timer into a byte.
+pub const fn pwm_min_frequency() -> u32 {
+    CPU_FREQUENCY / (PWM_PRESCALERS[PWM_PRESCALERS.len() - 1] * 257) + 1
+}
+
+/// Highest PWM frequency in Hz, at which the timer only counts to 1 without a prescaler.
+pub const fn pwm_max_frequency() -> u32 {
+    CPU_FREQUENCY / 2
+}
+
+//Oldest firmware revision experiment_read_many is enabled for
+#[cfg(feature = "experimental")]
+const READ_MANY_MIN_FIRMWARE: (u32, u32, u32) = (1, 0, 0);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Port {
     Port0,
     Port1,
 }
 
+impl Port {
+    fn index(self) -> usize {
+        match self {
+            Port::Port0 => 0,
+            Port::Port1 => 1,
+        }
+    }
+}
+
+impl TryFrom<u8> for Port {
+    type Error = B15FCommandError;
+
+    /// Converts a runtime port number, returning B15FCommandError::InvalidPort if it is neither 0 nor 1.
+    fn try_from(port: u8) -> Result<Self, Self::Error> {
+        match port {
+            0 => Ok(Port::Port0),
+            1 => Ok(Port::Port1),
+            _ => Err(B15FCommandError::InvalidPort(port)),
+        }
+    }
+}
+
+impl From<Port> for u8 {
+    fn from(port: Port) -> Self {
+        port.index() as u8
+    }
+}
+
+/// Values for every output of the board, see [`B15F::configure_outputs`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutputConfig {
+    /// The values of digital port 0 and 1.
+    pub digital: [u8; 2],
+    /// The values of analog output 0 and 1, must be between 0 and 1023.
+    pub analog: [u16; 2],
+}
+
+/// The PWM settings, see [`B15F::pwm_config`].
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct PwmConfig {
+    /// The requested frequency, `None` if it was not set yet.
+    pub frequency: Option<f32>,
+    /// The timer top value the board answered to the frequency.
+    pub top: Option<u8>,
+    /// The PWM value, the duty cycle is `value / top`.
+    pub value: Option<u8>,
+    /// Whether the values were read back from the board or are the host-side cache.
+    pub confirmed: bool,
+}
+
+/// Which pin of a digital port a bit of the port value stands for, see [`B15F::set_bit_order`].
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitOrder {
+    /// Bit 0 is pin 0, like the standard firmware and wiring.
+    #[default]
+    Lsb,
+    /// Bit 7 is pin 0, both written and read values are reversed compared to [`BitOrder::Lsb`].
+    Msb,
+}
+
+impl BitOrder {
+    /// Converts between the value of the port and the value sent to the board (both directions
+    /// are the same, as reversing twice is a no-op).
+    fn apply(self, value: u8) -> u8 {
+        match self {
+            BitOrder::Lsb => value,
+            BitOrder::Msb => value.reverse_bits(),
+        }
+    }
+}
+
+/// How often requests are repeated after an IO error or a timeout, see [`B15F::set_retry_policy`].
+///
+/// A failed read may happen after the request reached the board, so repeating a write executes it
+/// twice. Only reads (`test`, `integration_test`, `digital_read`, `analog_read`, the analog batch
+/// reads, `read_dip_switch` and `board_info`) are retried unless `writes` is set, the self test and
+/// the memory requests count as writes.
+///
+/// Never retried are requests whose partial answer is returned or consumed while it arrives: the
+/// `*_partial` reads, `experiment_read_many`, the ADC/DAC stroke, `raw_command` and
+/// `probe_command`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    /// Additional attempts after the first one failed, 0 disables retries.
+    pub retries: u32,
+    /// How long to wait before each retry.
+    pub backoff: Duration,
+    /// Whether writes are retried as well.
+    pub writes: bool,
+}
+
+/// The shape of a signal generated by [`B15F::analog_waveform`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Waveform {
+    /// High for the first half of the period, then low.
+    Square,
+    /// Rising for the first half of the period, then falling.
+    Triangle,
+    /// Rising over the whole period, then dropping back to 0.
+    Sawtooth,
+}
+
+impl Waveform {
+    /// The value at `phase` (0 to 1) of a period for a signal between 0 and `amplitude`.
+    fn value(self, phase: f32, amplitude: u16) -> u16 {
+        let level = match self {
+            Waveform::Square if phase < 0.5 => 1.0,
+            Waveform::Square => 0.0,
+            Waveform::Triangle => 1.0 - (2.0 * phase - 1.0).abs(),
+            Waveform::Sawtooth => phase,
+        };
+        (level * amplitude as f32).round() as u16
+    }
+}
+
+/// A channel and its value or why it couldn't be read, see [`B15F::read_analog_batch_partial`].
+pub type ChannelReading = (u8, Result<u16, B15FCommandError>);
+
+/// The outcome of a connection test, see [`B15F::test_detailed`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TestResult {
+    /// The random byte sent to the board.
+    pub sent: u8,
+    /// The byte the board echoed.
+    pub received: u8,
+    /// Whether the echo matched.
+    pub ok: bool,
+}
+
+/// Maps a linear output intensity to a DAC value, see [`B15F::set_output_curve`].
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputCurve {
+    /// The DAC value is proportional to the intensity.
+    #[default]
+    Linear,
+    /// The intensity is raised to the given exponent before scaling (e.g. 2.2 for LEDs).
+    Gamma(f32),
+    /// DAC values for evenly spaced intensities from 0% to 100%, interpolated linearly in between.
+    Table(Vec<u16>),
+}
+
+impl OutputCurve {
+    fn validate(&self) -> Result<(), B15FCommandError> {
+        match self {
+            OutputCurve::Linear => Ok(()),
+            OutputCurve::Gamma(gamma) if !(gamma.is_finite() && *gamma >= 0.0) => {
+                Err(B15FCommandError::ValueOutOfRange {
+                    value: (*gamma).into(),
+                    min: 0.0,
+                    max: f32::MAX.into(),
+                })
+            }
+            OutputCurve::Gamma(_) => Ok(()),
+            //an empty table has no value to interpolate
+            OutputCurve::Table(table) if table.is_empty() => {
+                Err(B15FCommandError::ValueOutOfRange {
+                    value: 0.0,
+                    min: 1.0,
+                    max: f64::INFINITY,
+                })
+            }
+            OutputCurve::Table(table) => {
+                table.iter().try_for_each(|value| check_range(*value, 1023))
+            }
+        }
+    }
+
+    /// Maps `fraction` (0.0 - 1.0) to a DAC value between 0 and 1023.
+    pub fn apply(&self, fraction: f32) -> u16 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match self {
+            OutputCurve::Linear => (fraction * 1023.0).round() as u16,
+            OutputCurve::Gamma(gamma) => (fraction.powf(*gamma) * 1023.0).round() as u16,
+            OutputCurve::Table(table) => match table.len() {
+                0 => 0,
+                1 => table[0],
+                len => {
+                    let position = fraction * (len - 1) as f32;
+                    let lower = (position.floor() as usize).min(len - 2);
+                    let weight = position - lower as f32;
+                    let low = table[lower] as f32;
+                    let high = table[lower + 1] as f32;
+                    (low + (high - low) * weight).round() as u16
+                }
+            },
+        }
+    }
+}
+
 #[cfg(feature = "experimental")]
 bitflags! {
     pub struct ReadManyPorts: u16 {
@@ -97,10 +401,70 @@ impl ReadManyPorts {
 pub enum B15FCommandError {
     #[error("board error responded with error")]
     B15FError,
+    #[error("board rejected the request")]
+    BoardRejected,
+    #[error("{0} is not supported by the board firmware")]
+    UnsupportedByFirmware(&'static str),
+    #[error("channel {channel} invalid, valid {valid:?}")]
+    InvalidChannel {
+        channel: u8,
+        valid: RangeInclusive<u8>,
+    },
+    #[error("value {value} out of range {min}..={max}")]
+    ValueOutOfRange { value: f64, min: f64, max: f64 },
+    #[error("port {0} invalid, valid 0..=1")]
+    InvalidPort(u8),
+    #[error("input buffer overrun, {pending} bytes pending, data was probably lost")]
+    BufferOverrun { pending: u32 },
     #[error("Serial port error: {0}")]
     SerialPortError(#[from] serialport::Error),
+    #[error("port {} failed: {source}", u8::from(*port))]
+    PortFailed {
+        port: Port,
+        source: Box<B15FCommandError>,
+    },
+    #[error("step {step} failed: {source}")]
+    StepFailed {
+        step: u16,
+        source: Box<B15FCommandError>,
+    },
+    #[error("timeout: {0}")]
+    Timeout(std::io::Error),
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
+    IoError(std::io::Error),
+    #[error("board is disconnected")]
+    Disconnected,
+}
+
+impl From<std::io::Error> for B15FCommandError {
+    fn from(err: std::io::Error) -> Self {
+        if err.kind() == std::io::ErrorKind::TimedOut {
+            B15FCommandError::Timeout(err)
+        } else {
+            B15FCommandError::IoError(err)
+        }
+    }
+}
+
+impl B15FCommandError {
+    /// A short, stable name of the error variant, used as metrics label.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            B15FCommandError::B15FError => "board",
+            B15FCommandError::BoardRejected => "rejected",
+            B15FCommandError::UnsupportedByFirmware(_) => "unsupported",
+            B15FCommandError::InvalidChannel { .. } => "invalid_channel",
+            B15FCommandError::ValueOutOfRange { .. } => "value_out_of_range",
+            B15FCommandError::InvalidPort(_) => "invalid_port",
+            B15FCommandError::BufferOverrun { .. } => "buffer_overrun",
+            B15FCommandError::SerialPortError(_) => "serial_port",
+            B15FCommandError::PortFailed { source, .. } => source.kind(),
+            B15FCommandError::StepFailed { source, .. } => source.kind(),
+            B15FCommandError::Timeout(_) => "timeout",
+            B15FCommandError::IoError(_) => "io",
+            B15FCommandError::Disconnected => "disconnected",
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -111,6 +475,10 @@ pub enum B15FInitError {
     DeviceNotFound,
     #[error("device not supported")]
     DeviceNotSupported,
+    #[error(
+        "permission denied for {port}, on Linux add the user to the dialout group and log in again"
+    )]
+    PermissionDenied { port: String },
     #[error("Serial port error: {0}")]
     SerialPortError(#[from] serialport::Error),
 }
@@ -119,354 +487,2037 @@ pub struct B15F<P>
 where
     P: serialport::SerialPort,
 {
-    port: P,
+    //only taken by into_inner, so the reset on drop can be skipped without unsafe code
+    port: Option<P>,
+    port_name: Option<String>,
+    baud: u32,
+    output_curves: [OutputCurve; 2],
+    analog_channels: u8,
+    analog_reference: f32,
+    calibration: CalibrationTable,
+    metrics: Metrics,
+    latency: Option<Duration>,
+    pwm: PwmConfig,
+    dip_switch: Option<u8>,
+    digital_outputs: [Option<u8>; 2],
+    retry: RetryPolicy,
+    reset_on_drop: bool,
+    bit_order: BitOrder,
+    command_delay: Duration,
+    auto_flush: bool,
+    read_timeout: Option<Duration>,
+    board_info: Option<BoardInfo>,
+}
+
+impl<P: serialport::SerialPort> std::fmt::Debug for B15F<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("B15F")
+            .field("port_name", &self.port_name)
+            .field("baud", &self.baud)
+            .field("timeout", &self.timeout())
+            .field("read_timeout", &self.read_timeout)
+            .field("retry", &self.retry)
+            .field("bit_order", &self.bit_order)
+            .field("reset_on_drop", &self.reset_on_drop)
+            .field("command_delay", &self.command_delay)
+            .field("auto_flush", &self.auto_flush)
+            .finish_non_exhaustive()
+    }
 }
 
 impl B15F<NativePort> {
     pub fn open_port(port_name: &str) -> Result<B15F<NativePort>, B15FInitError> {
-        let port = serialport::new(port_name, BAUD)
-            .timeout(Duration::from_millis(5000))
-            .open_native()
-            .map_err(B15FInitError::SerialPortError)?;
-        B15F::from(port)
+        B15F::open_port_with_baud(port_name, BAUD)
     }
 
-    ///Automatically detects the B15F board and returns an instance of B15F.
-    pub fn instance() -> Option<B15F<NativePort>> {
-        let mut ports = serialport::available_ports().ok()?;
-        ports.sort_unstable_by_key(port_priority);
-        for port in ports {
-            #[cfg(feature = "log")]
-            debug!("[Discover] Check for B15 board on {}", port.port_name);
-            let board = B15F::open_port(&port.port_name)
-                .inspect_err(|err| {
-                    #[cfg(feature = "log")]
-                    debug!("[Discover] Failed to open {}: {}", port.port_name, err);
-                })
-                .ok()
-                .and_then(|mut board| {
-                    board
-                        .test()
-                        .inspect_err(|err| {
-                            #[cfg(feature = "log")]
-                            debug!("[Discover] Test failed for {}: {}", port.port_name, err);
-                        })
-                        .ok()?;
-                    Some(board)
-                });
-            if let Some(board) = board {
-                #[cfg(feature = "log")]
-                debug!("[Discover] Choose B15 board on {}", port.port_name);
-                return Some(board);
-            }
-        }
-        None
+    /// Opens the board on the given port with a custom baud rate instead of the default 57600,
+    /// e.g. for rebuilt firmware images or the b32 experimental board.
+    ///
+    /// A baud rate not matching the firmware usually doesn't cause an IO error, the board just
+    /// receives garbage. The initial `test()` then fails and this returns
+    /// B15FInitError::DeviceNotSupported (or a timeout), which is the hint to check the baud rate.
+    pub fn open_port_with_baud(
+        port_name: &str,
+        baud: u32,
+    ) -> Result<B15F<NativePort>, B15FInitError> {
+        B15F::open(port_name, baud, DEFAULT_TIMEOUT)
     }
-}
 
-impl<P> B15F<P>
-where
-    P: serialport::SerialPort,
-{
-    pub fn from(port: P) -> Result<B15F<P>, B15FInitError> {
-        let mut board = B15F { port };
-        let pass = board.test()?;
-        if !pass {
-            return Err(B15FInitError::DeviceNotSupported);
-        }
-        Ok(board)
+    /// Opens the board on the given port with a custom read/write timeout instead of the default 5 seconds.
+    ///
+    /// See [`B15F::set_timeout`] to change it later.
+    pub fn open_port_with_timeout(
+        port_name: &str,
+        timeout: Duration,
+    ) -> Result<B15F<NativePort>, B15FInitError> {
+        B15F::open(port_name, BAUD, timeout)
     }
 
-    pub fn test(&mut self) -> Result<bool, B15FCommandError> {
-        let rand = random::<u8>();
-        let data = [RQ_TEST, rand];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        let mut response = [0u8; 2];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        if response[0] != MSG_OK {
-            return Err(B15FCommandError::B15FError);
-        }
-        let response = response[1];
+    fn open(
+        port_name: &str,
+        baud: u32,
+        timeout: Duration,
+    ) -> Result<B15F<NativePort>, B15FInitError> {
+        B15F::from(B15F::open_native(port_name, baud, timeout)?)
+    }
 
-        let pass = response == rand;
-        Ok(pass)
+    fn open_native(
+        port_name: &str,
+        baud: u32,
+        timeout: Duration,
+    ) -> Result<NativePort, B15FInitError> {
+        serialport::new(port_name, baud)
+            .timeout(timeout)
+            .open_native()
+            .map_err(|err| open_error(port_name, err))
     }
 
-    /// Writes a digital value to a specified port.
-    ///
-    /// This function sends a request to the specified digital port to write a given value.
-    /// The port number must be either 0 or 1, otherwise, the function will panic.
-    /// The function writes the request and the value to the port, flushes the port to ensure the request is sent,
-    /// then reads the response from the port.
-    /// If the response is MSG_OK, the function returns Ok(()), otherwise, it returns a B15FCommandError::B15FError.
-    ///
-    /// # Arguments
-    ///
-    /// * `port` - A u8 representing the port number to write to. Must be either 0 or 1.
-    /// * `value` - A u8 representing the value to write to the port.
-    ///
-    /// # Returns
-    ///
-    /// * `Result<(), B15FCommandError>` - On success, returns Ok(()). On failure, returns a B15FCommandError.
-    ///
-    /// # Panics
+    /// Reopens the serial port after the connection broke, e.g. because the USB adapter was
+    /// unplugged and plugged in again.
     ///
-    /// * If the port number is not 0 or 1, the function will panic.
+    /// The port is opened by the same name with the same baud rate and timeout, then tested.
+    /// The adapter must come back under the same name, which is not guaranteed on every system.
+    /// If PWM was disabled with [`B15F::pwm_disable`], it is disabled again.
     ///
     /// # Errors
     ///
-    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
-    /// * If the response from the port is not MSG_OK, the function will return a B15FCommandError::B15FError.
-    pub fn digital_write(&mut self, port: Port, value: u8) -> Result<(), B15FCommandError> {
-        let request = match port {
-            Port::Port0 => RQ_DIGITAL_WRITE_0,
-            Port::Port1 => RQ_DIGITAL_WRITE_1,
-        };
-        let data = [request, value];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
-
-        let mut response = [0u8];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        let response = response[0];
-        if response == MSG_OK {
-            Ok(())
-        } else {
-            Err(B15FCommandError::B15FError)
+    /// * If the port has no name, the function will return a B15FInitError::DeviceNotFound.
+    /// * If the user may not access the port, the function will return a B15FInitError::PermissionDenied.
+    /// * If the port can't be opened, the function will return a B15FInitError::SerialPortError.
+    /// * If the board fails the test, the function will return a B15FInitError::DeviceNotSupported.
+    /// * If PWM can't be disabled again, the function will return a B15FInitError::CommandError.
+    pub fn reconnect(&mut self) -> Result<(), B15FInitError> {
+        let port_name = self
+            .port_name
+            .clone()
+            .ok_or(B15FInitError::DeviceNotFound)?;
+        self.port = Some(B15F::open_native(&port_name, self.baud, self.timeout())?);
+        //the board may have been reset or flashed in between
+        self.digital_outputs = [None; 2];
+        self.board_info = None;
+        if !self.test()? {
+            return Err(B15FInitError::DeviceNotSupported);
+        }
+        if self.pwm.frequency == Some(0.0) {
+            self.pwm_disable()?;
         }
+        Ok(())
     }
 
-    /// Reads the digital value from a specified port.
-    ///
-    /// This function sends a request to the specified digital port to read its current value.
-    /// The port number must be either 0 or 1, otherwise, the function will panic.
-    /// The function writes the request to the port, flushes the port to ensure the request is sent,
-    /// then reads the response from the port.
-    /// The response is a single byte, which is then reversed (as the device sends the bits in reverse order).
-    ///
-    /// # Arguments
+    /// Returns a builder to open a board with custom connection options.
+    pub fn builder() -> B15FBuilder {
+        B15FBuilder::default()
+    }
+
+    ///Automatically detects the B15F board and returns an instance of B15F.
     ///
-    /// * `port` - A u8 representing the port number to read from. Must be either 0 or 1.
+    /// USB ports are only probed if their vendor and product id is in [`KNOWN_USB_IDS`], ports
+    /// without USB ids are probed by writing a test request. Each probe waits at most 300 ms for an
+    /// answer, the found board uses the default timeout. Use [`B15F::instance_by_usb`] for boards
+    /// on other USB adapters.
+    pub fn instance() -> Option<B15F<NativePort>> {
+        B15F::discover().next()
+    }
+
+    /// Like [`B15F::instance`] but tells why no board was found.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// * `Result<u8, B15FCommandError>` - On success, returns the read value as a u8. On failure, returns a B15FCommandError.
+    /// * If a port was found but no board answered, the function will return the error of the last probed port.
+    /// * If the ports can't be listed, the function will return a B15FInitError::SerialPortError.
+    /// * If there are no ports at all, the function will return a B15FInitError::DeviceNotFound.
+    pub fn try_instance() -> Result<B15F<NativePort>, B15FInitError> {
+        DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, port_priority)
+            .retain(maybe_b15f)
+            .try_next()
+    }
+
+    /// Like [`B15F::instance`] but also returns what happened on every probed port, e.g. for a GUI
+    /// showing why no board was found without enabling logging.
     ///
-    /// # Panics
+    /// The attempts are in probing order and end with the port the board was found on. If the
+    /// ports can't be listed at all, there are no attempts, [`B15F::try_instance`] reports why.
+    pub fn instance_verbose() -> (Option<B15F<NativePort>>, Vec<DiscoveryAttempt>) {
+        let mut attempts = Vec::new();
+        let ports = DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, port_priority)
+            .retain(maybe_b15f)
+            .ports;
+        for port in ports {
+            let result = B15F::probe(&port, BAUD, DEFAULT_TIMEOUT);
+            attempts.push(DiscoveryAttempt::new(&port, &result));
+            if let Ok(board) = result {
+                return (Some(board), attempts);
+            }
+        }
+        (None, attempts)
+    }
+
+    /// Like [`B15F::instance`] but probes the ports in the order of a custom priority, lower first.
     ///
-    /// * If the port number is not 0 or 1, the function will panic.
+    /// The default prefers USB over PCI, Bluetooth and unknown ports. A custom order helps if the
+    /// board sits on an adapter reported as unknown (e.g. some CH340) behind an unrelated USB device.
     ///
-    /// # Errors
+    /// ```no_run
+    /// use b15f::B15F;
     ///
-    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
-    pub fn digital_read(&mut self, port: Port) -> Result<u8, B15FCommandError> {
-        self.send_digital_read_request(port)?;
-        self.read_digital_response()
+    /// let board = B15F::instance_with_priority(|port| {
+    ///     if port.port_name == "/dev/ttyS4" { 0 } else { 1 }
+    /// });
+    /// ```
+    pub fn instance_with_priority(
+        priority: impl FnMut(&serialport::SerialPortInfo) -> u8,
+    ) -> Option<B15F<NativePort>> {
+        DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, priority)
+            .retain(maybe_b15f)
+            .next()
     }
 
-    fn send_digital_read_request(&mut self, port: Port) -> Result<(), B15FCommandError> {
-        let request = match port {
-            Port::Port0 => RQ_DIGITAL_READ_0,
-            Port::Port1 => RQ_DIGITAL_READ_1,
-        };
-        let data = [request];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
-        Ok(())
+    /// Like [`B15F::instance`] but only probes USB ports with the given vendor and product id.
+    ///
+    /// Nothing is written to other ports, which is faster on hosts with many serial devices and
+    /// doesn't disturb unrelated hardware.
+    pub fn instance_by_usb(vid: u16, pid: u16) -> Option<B15F<NativePort>> {
+        DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, port_priority)
+            .retain(|port| match &port.port_type {
+                SerialPortType::UsbPort(usb) => usb.vid == vid && usb.pid == pid,
+                _ => false,
+            })
+            .next()
     }
 
-    fn read_digital_response(&mut self) -> Result<u8, B15FCommandError> {
-        let mut response = [0u8];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        let response = response[0].reverse_bits();
-        Ok(response)
+    /// Detects every connected B15F board, e.g. for a lab with several stations on one host.
+    ///
+    /// The boards are ordered like the ports are probed, USB devices first.
+    pub fn instance_all() -> Vec<B15F<NativePort>> {
+        B15F::discover().collect()
     }
 
-    /// Writes an analog value to a specified port.
-    ///
-    /// This function sends a request to the specified analog port to write a given value.
-    /// The port number must be either 0 or 1, otherwise, the function will panic.
-    /// The value must be between 0 and 1023, otherwise, the function will panic.
-    /// The function writes the request and the value to the port, flushes the port to ensure the request is sent,
-    /// then reads the response from the port.
-    /// If the response is MSG_OK, the function returns Ok(()), otherwise, it returns a B15FCommandError::B15FError.
-    ///
-    /// # Arguments
-    ///
-    /// * `port` - A u8 representing the port number to write to. Must be either 0 or 1.
-    /// * `value` - A u16 representing the value to write to the port. Must be between 0 and 1023.
+    /// Probes the candidate ports lazily in priority order and yields every board that passes the
+    /// test, see [`B15F::list_candidate_ports`].
     ///
-    /// # Returns
+    /// A port is only opened when the next board is requested, so taking a few boards leaves the
+    /// remaining ports untouched. [`B15F::instance`] takes the first board, [`B15F::instance_all`]
+    /// collects them all.
+    pub fn discover() -> impl Iterator<Item = B15F<NativePort>> {
+        B15F::discovered().into_iter()
+    }
+
+    /// Returns the boards connected to this machine as an iterable.
     ///
-    /// * `Result<(), B15FCommandError>` - On success, returns Ok(()). On failure, returns a B15FCommandError.
+    /// The ports are filtered like for [`B15F::instance`] and probed lazily in priority order while
+    /// iterating, so stopping early avoids opening the remaining ports. Every call to `into_iter`
+    /// enumerates the ports again, so a [`Discovered`] can be stored and iterated multiple times.
+    pub fn discovered() -> Discovered {
+        Discovered
+    }
+
+    /// Lists the ports [`B15F::instance`] would probe, in the same order, without opening any.
     ///
-    /// # Panics
+    /// Meant for tools letting the user pick a port. Errors while listing the ports are only
+    /// logged and return an empty list, [`B15F::try_instance`] reports them.
+    pub fn list_candidate_ports() -> Vec<serialport::SerialPortInfo> {
+        DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, port_priority)
+            .retain(maybe_b15f)
+            .ports
+            .collect()
+    }
+
+    /// Opens the port at `index` of [`B15F::list_candidate_ports`].
     ///
-    /// * If the port number is not 0 or 1, the function will panic.
-    /// * If the value is not between 0 and 1023, the function will panic.
+    /// Device names like `/dev/ttyUSB1` may change between reboots, the position in the candidate
+    /// list is stable as long as the same adapters are plugged in. Unlike [`B15F::instance`], no
+    /// other candidate is tried if the port at `index` isn't a board.
     ///
     /// # Errors
     ///
-    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
-    /// * If the response from the port is not MSG_OK, the function will return a B15FCommandError::B15FError.
-    pub fn analog_write(&mut self, port: Port, value: u16) -> Result<(), B15FCommandError> {
-        let request = match port {
-            Port::Port0 => RQ_ANALOG_WRITE_0,
-            Port::Port1 => RQ_ANALOG_WRITE_1,
-        };
-        if value > 1023 {
-            panic!("analog write value must be between 0 and 1023")
-        }
-        let data = [request, (value & 0xFF) as u8, (value >> 8) as u8];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
+    /// * If there are no more than `index` candidates, the function will return a B15FInitError::DeviceNotFound.
+    /// * Same as [`B15F::open_port`] otherwise.
+    pub fn open_port_index(index: usize) -> Result<B15F<NativePort>, B15FInitError> {
+        let port = B15F::list_candidate_ports()
+            .into_iter()
+            .nth(index)
+            .ok_or(B15FInitError::DeviceNotFound)?;
+        B15F::open_port(&port.port_name)
+    }
 
-        let mut response = [0u8];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        let response = response[0];
-        if response == MSG_OK {
-            Ok(())
-        } else {
-            Err(B15FCommandError::B15FError)
+    fn probe(
+        port: &serialport::SerialPortInfo,
+        baud: u32,
+        timeout: Duration,
+    ) -> Result<B15F<NativePort>, B15FInitError> {
+        #[cfg(feature = "log")]
+        debug!("[Discover] Check for B15 board on {}", port.port_name);
+        //test with the short timeout, the configured one only applies once the board answered
+        let board = B15F::open_native(&port.port_name, baud, DISCOVERY_TIMEOUT.min(timeout))
+            .inspect_err(|_err| {
+                #[cfg(feature = "log")]
+                debug!("[Discover] Failed to open {}: {}", port.port_name, _err);
+            })
+            .and_then(|native| {
+                let mut board = B15F::from(native).inspect_err(|_err| {
+                    #[cfg(feature = "log")]
+                    debug!("[Discover] Test failed for {}: {}", port.port_name, _err);
+                })?;
+                board.set_timeout(timeout)?;
+                Ok(board)
+            });
+        #[cfg(feature = "log")]
+        if board.is_ok() {
+            debug!("[Discover] Choose B15 board on {}", port.port_name);
         }
+        board
     }
+}
 
-    /// This is an experimental function sending multiple read requests to the board before reading the response.
-    /// It slightly reduces the latency compared to sending a single request per port.
-    /// Depending on the b15 implementation, it may not work as expected (my b32 experimental board works fine).
-    #[cfg(feature = "experimental")]
-    pub fn experiment_read_many(
-        &mut self,
-        ports: ReadManyPorts,
-    ) -> Result<([u8; 2], [u16; 7]), B15FCommandError> {
-        
-        if ports.contains(ReadManyPorts::Digital0) {
-            self.send_digital_read_request(Port::Port0)?;
-        }
-        if ports.contains(ReadManyPorts::Digital1) {
-            self.send_digital_read_request(Port::Port1)?;
-        }
-        for port in 0..8_u8 {
-            let many_port = ReadManyPorts::from_analog(port);
-            if ports.contains(many_port) {
-                self.send_analog_read_request(port)?;
-            }
-        }
-        self.port.flush()?;
+/// Opens the board on a port listed by [`serialport::available_ports`] or
+/// [`B15F::list_candidate_ports`], e.g. after the user picked one in a GUI.
+///
+/// The port is opened like [`B15F::open_port`], a failing test returns
+/// B15FInitError::DeviceNotSupported.
+impl TryFrom<&serialport::SerialPortInfo> for B15F<NativePort> {
+    type Error = B15FInitError;
 
-        let mut digital = [0; 2];
-        let mut analog = [0; 7];
-        
-        if ports.contains(ReadManyPorts::Digital0) {
-            digital[0] = self.read_digital_response()?;
-        }
-        if ports.contains(ReadManyPorts::Digital1) {
-            digital[1] = self.read_digital_response()?;
+    fn try_from(port: &serialport::SerialPortInfo) -> Result<Self, Self::Error> {
+        B15F::open_port(&port.port_name)
+    }
+}
+
+/// What happened on a port probed by [`B15F::instance_verbose`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiscoveryAttempt {
+    pub port_name: String,
+    /// Whether the port could be opened.
+    pub opened: bool,
+    /// Whether the board answered the test correctly, `None` if it wasn't tested or didn't answer.
+    pub test_passed: Option<bool>,
+    /// Why the port was rejected.
+    pub error: Option<String>,
+}
+
+impl DiscoveryAttempt {
+    fn new(
+        port: &serialport::SerialPortInfo,
+        result: &Result<B15F<NativePort>, B15FInitError>,
+    ) -> Self {
+        let (opened, test_passed) = match result {
+            Ok(_) => (true, Some(true)),
+            Err(B15FInitError::DeviceNotSupported) => (true, Some(false)),
+            Err(B15FInitError::CommandError(_)) => (true, None),
+            Err(_) => (false, None),
+        };
+        DiscoveryAttempt {
+            port_name: port.port_name.clone(),
+            opened,
+            test_passed,
+            error: result.as_ref().err().map(ToString::to_string),
         }
-        for port in 0..8_u8 {
-            if ports.contains(ReadManyPorts::from_analog(port)) {
-                analog[port as usize] = self.read_analog_response()?;
-            }
+    }
+}
+
+/// The boards connected to this machine, see [`B15F::discovered`].
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Discovered;
+
+impl IntoIterator for Discovered {
+    type Item = B15F<NativePort>;
+    type IntoIter = DiscoveredBoards;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DiscoveredBoards::new(BAUD, DEFAULT_TIMEOUT, port_priority).retain(maybe_b15f)
+    }
+}
+
+impl IntoIterator for &Discovered {
+    type Item = B15F<NativePort>;
+    type IntoIter = DiscoveredBoards;
+
+    fn into_iter(self) -> Self::IntoIter {
+        (*self).into_iter()
+    }
+}
+
+/// Iterator probing the candidate ports one by one, yielding every board that passes the test.
+pub struct DiscoveredBoards {
+    ports: std::vec::IntoIter<serialport::SerialPortInfo>,
+    baud: u32,
+    timeout: Duration,
+    last_error: Option<B15FInitError>,
+}
+
+impl DiscoveredBoards {
+    fn new(
+        baud: u32,
+        timeout: Duration,
+        priority: impl FnMut(&serialport::SerialPortInfo) -> u8,
+    ) -> Self {
+        let mut last_error = None;
+        let mut ports = serialport::available_ports().unwrap_or_else(|err| {
+            #[cfg(feature = "log")]
+            debug!("[Discover] Failed to list ports: {}", err);
+            last_error = Some(B15FInitError::SerialPortError(err));
+            Vec::new()
+        });
+        ports.sort_by_cached_key(priority);
+        DiscoveredBoards {
+            ports: ports.into_iter(),
+            baud,
+            timeout,
+            last_error,
         }
+    }
 
-        Ok((digital, analog))
+    /// Drops the ports not matching `filter` before anything is written to them.
+    fn retain(mut self, filter: impl FnMut(&serialport::SerialPortInfo) -> bool) -> Self {
+        self.ports = self.ports.filter(filter).collect::<Vec<_>>().into_iter();
+        self
     }
 
-    /// Reads the analog value from a specified port.
-    ///
-    /// This function sends a request to the specified analog port to read its current value.
-    /// The port number must be between 0 and 7, otherwise, the function will panic.
-    /// The function writes the request to the port, flushes the port to ensure the request is sent,
-    /// then reads the response from the port.
-    /// The response is a two-byte value, which is then converted to a u16 using little-endian byte order.
+    /// Returns the next board or why none was found.
+    fn try_next(&mut self) -> Result<B15F<NativePort>, B15FInitError> {
+        self.next().ok_or_else(|| {
+            self.last_error
+                .take()
+                .unwrap_or(B15FInitError::DeviceNotFound)
+        })
+    }
+}
+
+impl Iterator for DiscoveredBoards {
+    type Item = B15F<NativePort>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for port in self.ports.by_ref() {
+            match B15F::probe(&port, self.baud, self.timeout) {
+                Ok(board) => return Some(board),
+                Err(err) => self.last_error = Some(err),
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.ports.len()))
+    }
+}
+
+impl<P> B15F<P>
+where
+    P: serialport::SerialPort,
+{
+    pub fn from(port: P) -> Result<B15F<P>, B15FInitError> {
+        let mut board = B15F::with_port_unchecked(port);
+        let pass = board.test()?;
+        if !pass {
+            return Err(B15FInitError::DeviceNotSupported);
+        }
+        Ok(board)
+    }
+
+    /// Creates a board without the initial test request [`B15F::from`] sends, e.g. for firmware
+    /// without RQ_TEST or to drive a mock port without scripting the test.
+    pub fn with_port_unchecked(port: P) -> B15F<P> {
+        B15F {
+            port_name: port.name(),
+            baud: port.baud_rate().unwrap_or(BAUD),
+            port: Some(port),
+            output_curves: Default::default(),
+            analog_channels: DEFAULT_ANALOG_CHANNELS,
+            analog_reference: DEFAULT_ANALOG_REFERENCE,
+            calibration: CalibrationTable::default(),
+            metrics: Metrics::default(),
+            latency: None,
+            pwm: PwmConfig::default(),
+            dip_switch: None,
+            digital_outputs: [None; 2],
+            retry: RetryPolicy::default(),
+            reset_on_drop: false,
+            bit_order: BitOrder::default(),
+            command_delay: Duration::ZERO,
+            auto_flush: true,
+            read_timeout: None,
+            board_info: None,
+        }
+    }
+
+    /// Returns the serial port, e.g. to reconfigure it or to share it with other protocol code.
+    ///
+    /// The outputs are left as they are, even if [`B15F::set_reset_on_drop`] is enabled.
+    pub fn into_inner(mut self) -> P {
+        self.port
+            .take()
+            .expect("the port is only taken when the board is consumed")
+    }
+
+    fn port(&self) -> &P {
+        self.port
+            .as_ref()
+            .expect("the port is only taken when the board is consumed")
+    }
+
+    /// Returns the serial port, e.g. to enable hardware flow control or to drive the modem lines.
+    ///
+    /// Reading from or writing to the port directly, or changing its baud rate, desyncs the
+    /// protocol: the board may take the bytes as a request or answer a request the next command
+    /// doesn't expect. [`B15F::test`] resyncs it.
+    pub fn port_mut(&mut self) -> &mut P {
+        self.port
+            .as_mut()
+            .expect("the port is only taken when the board is consumed")
+    }
+
+    /// Returns the name of the serial port the board is connected to, if the port has one.
+    pub fn port_name(&self) -> Option<&str> {
+        self.port_name.as_deref()
+    }
+
+    /// Returns the command counters collected since the board was opened.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Resets all command counters to zero.
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    /// Returns the command counters in the Prometheus text exposition format.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn metrics_prometheus(&self) -> String {
+        self.metrics.to_prometheus()
+    }
+
+    /// Sends a request and reads the complete response, recording it in the metrics.
+    fn transact(&mut self, request: &[u8], response: &mut [u8]) -> Result<(), B15FCommandError> {
+        let started = Instant::now();
+        let result = self
+            .write_request(request)
+            .and_then(|_| self.read_response(response));
+        self.metrics.record(started.elapsed(), &result);
+        result
+    }
+
+    /// Sends a request which is answered with a single status byte and checks it for MSG_OK.
+    fn command_ok(&mut self, request: &[u8]) -> Result<(), B15FCommandError> {
+        self.with_retries(false, |board| {
+            let mut response = [0u8];
+            board.transact(request, &mut response)?;
+            proto::decode_status(&response)
+        })
+    }
+
+    /// Sets a pause between sending a request and reading its response, for firmware which drops
+    /// bytes when the host reads too early. Zero (no pause) by default.
+    ///
+    /// The delay adds to the latency of every command. Pipelined commands pause after every write
+    /// they send, e.g. once for a whole [`B15F::read_analog_batch`]. A few milliseconds usually
+    /// suffice.
+    pub fn set_command_delay(&mut self, delay: Duration) {
+        self.command_delay = delay;
+    }
+
+    /// Returns the pause between a request and reading its response.
+    pub fn command_delay(&self) -> Duration {
+        self.command_delay
+    }
+
+    /// Sets whether every request is flushed right after it was written, enabled by default.
+    ///
+    /// Flushing waits until the driver sent the request, which costs a syscall per command on some
+    /// drivers. Without it, rapid writes (e.g. [`B15F::analog_waveform`]) return sooner and the
+    /// driver sends them in larger chunks, but a request may still sit in the OS buffer when the
+    /// method returns. Call [`B15F::flush`] where the board has to have received the requests.
+    pub fn set_auto_flush(&mut self, auto_flush: bool) {
+        self.auto_flush = auto_flush;
+    }
+
+    /// Returns whether every request is flushed right after it was written.
+    pub fn auto_flush(&self) -> bool {
+        self.auto_flush
+    }
+
+    /// Waits until all written requests were sent, see [`B15F::set_auto_flush`].
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when flushing the port, the function will return a B15FCommandError::IoError.
+    pub fn flush(&mut self) -> Result<(), B15FCommandError> {
+        self.port_mut().flush()?;
+        Ok(())
+    }
+
+    /// Sets how requests are repeated after IO errors, by default they are not.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Returns how requests are repeated after IO errors.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// Sets the bit order of the digital port values for rebuilt firmware or different wiring.
+    /// Applies to all digital reads and writes, [`BitOrder::Lsb`] by default.
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        self.bit_order = order;
+    }
+
+    /// Returns the bit order of the digital port values.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Zeroes both analog outputs and the PWM value when the board is dropped, so hardware isn't
+    /// driven on after the program exits. Disabled by default.
+    ///
+    /// Errors while resetting are ignored, as there is nobody to report them to.
+    pub fn set_reset_on_drop(&mut self, reset: bool) {
+        self.reset_on_drop = reset;
+    }
+
+    /// Runs `attempt` and repeats it on IO errors and timeouts as allowed by the retry policy.
+    fn with_retries<T>(
+        &mut self,
+        idempotent: bool,
+        mut attempt: impl FnMut(&mut Self) -> Result<T, B15FCommandError>,
+    ) -> Result<T, B15FCommandError> {
+        let retries = if idempotent || self.retry.writes {
+            self.retry.retries
+        } else {
+            0
+        };
+        let mut result = attempt(self);
+        for _ in 0..retries {
+            if !matches!(
+                result,
+                Err(B15FCommandError::IoError(_) | B15FCommandError::Timeout(_))
+            ) {
+                break;
+            }
+            self.metrics.retries += 1;
+            std::thread::sleep(self.retry.backoff);
+            //drop what is left of the failed response
+            let _ = self.port().clear(ClearBuffer::Input);
+            result = attempt(self);
+        }
+        result
+    }
+
+    /// Checks how full the OS input buffer is before reading a longer response.
+    ///
+    /// When the host falls behind, the OS drops bytes once its buffer is full which silently desyncs
+    /// the protocol. A full buffer is reported as [`B15FCommandError::BufferOverrun`], a nearly full
+    /// buffer is counted in [`Metrics::input_buffer_warnings`] (and logged).
+    /// The buffer size differs between platforms and drivers and some can't report the pending bytes
+    /// at all, so this is a heuristic and can't catch every lost byte.
+    #[cfg(feature = "experimental")]
+    fn check_input_buffer(&mut self) -> Result<(), B15FCommandError> {
+        let Ok(pending) = self.port().bytes_to_read() else {
+            return Ok(());
+        };
+        if pending >= INPUT_BUFFER_SIZE {
+            return Err(B15FCommandError::BufferOverrun { pending });
+        }
+        if pending >= INPUT_BUFFER_WARN {
+            #[cfg(feature = "log")]
+            log::warn!("[Read] Input buffer nearly full: {} bytes pending", pending);
+            self.metrics.input_buffer_warnings += 1;
+        }
+        Ok(())
+    }
+
+    fn write_request(&mut self, request: &[u8]) -> Result<(), B15FCommandError> {
+        #[cfg(feature = "log")]
+        trace!("TX: {:02X?}", request);
+        self.port_mut().write_all(request)?;
+        if self.auto_flush {
+            self.port_mut().flush()?;
+        }
+        if !self.command_delay.is_zero() {
+            std::thread::sleep(self.command_delay);
+        }
+        Ok(())
+    }
+
+    /// Reads a response, on a failed read the input buffer is cleared so leftovers of the response
+    /// can't be mistaken for the answer to the next command.
+    fn read_response(&mut self, response: &mut [u8]) -> Result<(), B15FCommandError> {
+        let result = match self.read_timeout {
+            Some(read_timeout) => {
+                let timeout = self.port().timeout();
+                self.port_mut().set_timeout(read_timeout)?;
+                let result = self.read_frame(response);
+                let restored = self.port_mut().set_timeout(timeout);
+                result
+                    .map_err(B15FCommandError::from)
+                    .and_then(|_| restored.map_err(B15FCommandError::from))
+            }
+            None => self.read_frame(response).map_err(B15FCommandError::from),
+        };
+        if let Err(err) = result {
+            let _ = self.port().clear(ClearBuffer::Input);
+            return Err(err);
+        }
+        #[cfg(feature = "log")]
+        trace!("RX: {:02X?}", response);
+        Ok(())
+    }
+
+    /// Fills `frame` from the port, waiting at most the port timeout for the whole frame.
+    ///
+    /// Unlike `read_exact` this keeps the bytes of a partial read when some backends time out in
+    /// the middle of a frame and keeps reading until the deadline. A frame which is still
+    /// incomplete then fails with [`std::io::ErrorKind::TimedOut`] naming the bytes received.
+    fn read_frame(&mut self, frame: &mut [u8]) -> std::io::Result<()> {
+        //a timeout too large for an Instant (e.g. Duration::MAX) never expires
+        let deadline = Instant::now().checked_add(self.timeout());
+        let mut received = 0;
+        while received < frame.len() {
+            match self.port_mut().read(&mut frame[received..]) {
+                Ok(0) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        format!("port closed after {} of {} bytes", received, frame.len()),
+                    ))
+                }
+                Ok(len) => received += len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                //nothing at all within the timeout, the board didn't answer
+                Err(err) if received == 0 => return Err(err),
+                Err(err) if err.kind() == std::io::ErrorKind::TimedOut => {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            format!("timed out after {} of {} bytes", received, frame.len()),
+                        ));
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets how long reads and writes wait for the board before failing with a timeout.
+    ///
+    /// Shorter timeouts detect a disconnected board faster, slow USB serial adapters may need
+    /// longer ones for bulk reads.
+    ///
+    /// # Errors
+    ///
+    /// * If the serial port rejects the timeout, the function will return a B15FCommandError::SerialPortError.
+    pub fn set_timeout(&mut self, timeout: Duration) -> Result<(), B15FCommandError> {
+        self.port_mut().set_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the current read/write timeout.
+    pub fn timeout(&self) -> Duration {
+        self.port().timeout()
+    }
+
+    /// Sets a separate timeout for reading responses, `None` (the default) uses [`B15F::timeout`].
+    ///
+    /// Reads may legitimately take longer than writes while the board is busy, e.g. with a long
+    /// ADC/DAC stroke. A short [`B15F::set_timeout`] then still detects a dead link quickly on the
+    /// write. The port timeout is switched around every read and restored afterwards, even if the
+    /// read fails, which costs two extra calls into the serial driver per response.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// Returns the separate timeout for reading responses, see [`B15F::set_read_timeout`].
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Discards any bytes received but not read yet.
+    ///
+    /// Leftovers of a response (e.g. after a timeout in the middle of it) are otherwise read as the
+    /// answer to the next command, so every following read returns garbage. Failed reads and
+    /// [`B15F::test`] do this automatically, call it after other errors (e.g. a garbage value)
+    /// before continuing with the same board.
+    ///
+    /// # Errors
+    ///
+    /// * If the buffer can't be cleared, the function will return a B15FCommandError::SerialPortError.
+    pub fn flush_input(&mut self) -> Result<(), B15FCommandError> {
+        self.port().clear(ClearBuffer::Input)?;
+        Ok(())
+    }
+
+    /// Resets the board by pulsing the DTR line, to recover a hung board without unplugging it.
+    ///
+    /// Only works with firmware and adapters wiring DTR to the reset pin (like Arduino based
+    /// boards), other boards ignore it. Waits for the bootloader to start the firmware and flushes
+    /// whatever it sent. The board forgets its outputs, so the cached digital outputs and the PWM
+    /// settings are cleared. Use [`B15F::reset_board_with_timing`] if the default timing doesn't
+    /// fit the bootloader.
+    ///
+    /// # Errors
+    ///
+    /// * If the DTR line can't be set or the buffer can't be cleared, the function will return a B15FCommandError::SerialPortError.
+    pub fn reset_board(&mut self) -> Result<(), B15FCommandError> {
+        self.reset_board_with_timing(RESET_PULSE, BOOTLOADER_DELAY)
+    }
+
+    /// Like [`B15F::reset_board`] with a custom length of the DTR pulse and time to wait for the
+    /// bootloader afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::reset_board`].
+    pub fn reset_board_with_timing(
+        &mut self,
+        pulse: Duration,
+        bootloader_delay: Duration,
+    ) -> Result<(), B15FCommandError> {
+        self.port_mut().write_data_terminal_ready(false)?;
+        std::thread::sleep(pulse);
+        self.port_mut().write_data_terminal_ready(true)?;
+        std::thread::sleep(bootloader_delay);
+        self.flush_input()?;
+        self.digital_outputs = [None; 2];
+        self.pwm = PwmConfig::default();
+        Ok(())
+    }
+
+    /// Checks the connection by sending a random byte the board has to echo.
+    ///
+    /// The input buffer is flushed first, so this also resynchronizes a desynced connection.
+    pub fn test(&mut self) -> Result<bool, B15FCommandError> {
+        execute_as!(self, Command::Test, CommandResult::Test)
+    }
+
+    /// Like [`B15F::test`] but returns the sent and the echoed byte.
+    ///
+    /// On a marginal link the echo is often almost right, e.g. the same bit flipped every time,
+    /// which points at a wrong baud rate or a grounding problem.
+    pub fn test_detailed(&mut self) -> Result<TestResult, B15FCommandError> {
+        self.flush_input()?;
+        self.with_retries(true, |board| {
+            let sent = random::<u8>();
+            let data = proto::encode_test(sent);
+            let mut response = [0u8; 2];
+            board.transact(&data, &mut response)?;
+            let received = proto::decode_test_echo(&response)?;
+            Ok(TestResult {
+                sent,
+                received,
+                ok: received == sent,
+            })
+        })
+    }
+
+    /// Tests the firmware's integer arithmetic, not just the serial link like [`B15F::test`].
+    ///
+    /// A random 16-bit value is sent, the board answers with the value multiplied by 3.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the board calculated the expected value.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn integration_test(&mut self) -> Result<bool, B15FCommandError> {
+        execute_as!(self, Command::IntegrationTest, CommandResult::Test)
+    }
+
+    /// Runs the built-in self test of the board, useful during bring-up to tell a wiring fault from
+    /// a firmware crash.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the board answered MSG_OK, `Ok(false)` if it answered MSG_ERROR.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the board answers with anything else, the function will return a B15FCommandError::B15FError.
+    pub fn self_test(&mut self) -> Result<bool, B15FCommandError> {
+        execute_as!(self, Command::SelfTest, CommandResult::Test)
+    }
+
+    /// Writes a digital value to a specified port.
+    ///
+    /// This function sends a request to the specified digital port to write a given value.
+    /// The function writes the request and the value to the port, flushes the port to ensure the request is sent,
+    /// then reads the response from the port.
+    /// If the response is MSG_OK, the function returns Ok(()), if it is MSG_ERROR a B15FCommandError::BoardRejected,
+    /// otherwise a B15FCommandError::B15FError.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to write to, use `Port::try_from` for runtime port numbers.
+    /// * `value` - A u8 representing the value to write to the port.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), B15FCommandError>` - On success, returns Ok(()). On failure, returns a B15FCommandError.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If the response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn digital_write(&mut self, port: Port, value: u8) -> Result<(), B15FCommandError> {
+        self.execute(Command::DigitalWrite { port, value })?;
+        Ok(())
+    }
+
+    /// Writes the same value to both digital ports, e.g. to leave every output in a known state at
+    /// the end of an experiment.
+    ///
+    /// # Errors
+    ///
+    /// * If a write fails, the function will return a B15FCommandError::PortFailed with the port and
+    ///   the error. Port 1 isn't written if port 0 failed.
+    pub fn digital_write_both(&mut self, value: u8) -> Result<(), B15FCommandError> {
+        for port in [Port::Port0, Port::Port1] {
+            self.digital_write(port, value)
+                .map_err(|err| B15FCommandError::PortFailed {
+                    port,
+                    source: Box::new(err),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Drives every digital output low, see [`B15F::digital_write_both`].
+    pub fn all_low(&mut self) -> Result<(), B15FCommandError> {
+        self.digital_write_both(0x00)
+    }
+
+    /// Drives every digital output high, see [`B15F::digital_write_both`].
+    pub fn all_high(&mut self) -> Result<(), B15FCommandError> {
+        self.digital_write_both(0xFF)
+    }
+
+    /// Sets a single bit (0 - 7) of a digital port and leaves the others unchanged.
+    ///
+    /// The current byte is read with [`B15F::digital_read`], modified and written back, so this
+    /// takes two round trips. The board reads the port's input pins, the result only matches the
+    /// outputs if they are looped back.
+    ///
+    /// # Errors
+    ///
+    /// * If the bit is greater than 7, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * Same as [`B15F::digital_read`] and [`B15F::digital_write`].
+    pub fn digital_set_bit(
+        &mut self,
+        port: Port,
+        bit: u8,
+        value: bool,
+    ) -> Result<(), B15FCommandError> {
+        check_range(bit as u16, 7)?;
+        let current = self.digital_read(port)?;
+        self.digital_write(port, with_bit(current, bit, value))
+    }
+
+    /// Clears a single bit (0 - 7) of a digital port, see [`B15F::digital_set_bit`].
+    pub fn digital_clear_bit(&mut self, port: Port, bit: u8) -> Result<(), B15FCommandError> {
+        self.digital_set_bit(port, bit, false)
+    }
+
+    /// Sets a single bit (0 - 7) of a digital port based on [`B15F::last_written`] instead of
+    /// reading the port first, which saves a round trip.
+    ///
+    /// The remembered value is stale if anything else wrote the port (another process or a board
+    /// reset), the other bits are then overwritten with the remembered ones. Falls back to
+    /// [`B15F::digital_set_bit`] if nothing was written yet.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::digital_set_bit`].
+    pub fn digital_set_bit_cached(
+        &mut self,
+        port: Port,
+        bit: u8,
+        value: bool,
+    ) -> Result<(), B15FCommandError> {
+        let Some(current) = self.digital_outputs[port.index()] else {
+            return self.digital_set_bit(port, bit, value);
+        };
+        check_range(bit as u16, 7)?;
+        self.digital_write(port, with_bit(current, bit, value))
+    }
+
+    /// Returns the value last written to a digital port through this handle, `None` if the port
+    /// wasn't written yet or the board was reconnected since.
+    pub fn last_written(&self, port: Port) -> Option<u8> {
+        self.digital_outputs[port.index()]
+    }
+
+    /// Reads the digital value from a specified port.
+    ///
+    /// This function sends a request to the specified digital port to read its current value.
+    /// The function writes the request to the port, flushes the port to ensure the request is sent,
+    /// then reads the response from the port.
+    /// The response is a single byte, which is then reversed (as the device sends the bits in reverse order).
+    ///
+    /// The input pins are wired in reverse order to the output pins on the board, the reversal
+    /// compensates this: with the ports looped back, reading returns exactly the value written
+    /// with [`B15F::digital_write`]. Use [`B15F::digital_read_raw`] for the byte as sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to read from, use `Port::try_from` for runtime port numbers.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u8, B15FCommandError>` - On success, returns the read value as a u8. On failure, returns a B15FCommandError.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn digital_read(&mut self, port: Port) -> Result<u8, B15FCommandError> {
+        execute_as!(self, Command::DigitalRead { port }, CommandResult::Digital)
+    }
+
+    /// Reads a digital port like [`B15F::digital_read`] but returns the byte as the board sends it,
+    /// without reversing the bits or applying [`B15F::set_bit_order`].
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn digital_read_raw(&mut self, port: Port) -> Result<u8, B15FCommandError> {
+        execute_as!(
+            self,
+            Command::DigitalReadRaw { port },
+            CommandResult::Digital
+        )
+    }
+
+    fn send_digital_read_request(&mut self, port: Port) -> Result<(), B15FCommandError> {
+        self.write_request(&proto::encode_digital_read(port))
+    }
+
+    fn read_digital_response(&mut self) -> Result<u8, B15FCommandError> {
+        let mut response = [0u8];
+        self.read_response(&mut response)?;
+        Ok(self.bit_order.apply(proto::decode_digital(&response)?))
+    }
+
+    fn read_raw_digital_response(&mut self) -> Result<u8, B15FCommandError> {
+        let mut response = [0u8];
+        self.read_response(&mut response)?;
+        proto::decode_digital_raw(&response)
+    }
+
+    /// Reads the position of the DIP switches, one bit per switch.
+    ///
+    /// Like the digital ports the board sends the bits in reverse order, they are reversed so bit 0
+    /// is switch 1. The value is also remembered for [`B15F::dip_switch_bit`].
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn read_dip_switch(&mut self) -> Result<u8, B15FCommandError> {
+        execute_as!(self, Command::ReadDipSwitch, CommandResult::DipSwitch)
+    }
+
+    /// Returns whether switch `index` (0 - 7, bit 0 is switch 1) was on at the last [`B15F::read_dip_switch`].
+    ///
+    /// Returns false if the switches were never read or the index is out of range.
+    pub fn dip_switch_bit(&self, index: u8) -> bool {
+        match self.dip_switch {
+            Some(value) if index < 8 => value & (1 << index) != 0,
+            _ => false,
+        }
+    }
+
+    /// Writes an analog value to a specified port.
+    ///
+    /// This function sends a request to the specified analog port to write a given value.
+    /// The value must be between 0 and 1023, otherwise, the function will return an error.
+    /// The function writes the request and the value to the port, flushes the port to ensure the request is sent,
+    /// then reads the response from the port.
+    /// If the response is MSG_OK, the function returns Ok(()), if it is MSG_ERROR a B15FCommandError::BoardRejected,
+    /// otherwise a B15FCommandError::B15FError.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to write to, use `Port::try_from` for runtime port numbers.
+    /// * `value` - A u16 representing the value to write to the port. Must be between 0 and 1023.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), B15FCommandError>` - On success, returns Ok(()). On failure, returns a B15FCommandError.
+    ///
+    /// # Errors
+    ///
+    /// * If the value is not between 0 and 1023, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If the response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn analog_write(&mut self, port: Port, value: u16) -> Result<(), B15FCommandError> {
+        self.execute(Command::AnalogWrite { port, value })?;
+        Ok(())
+    }
+
+    /// Sweeps an analog output linearly from `from` to `to` in `steps` steps, e.g. to generate a
+    /// ramp signal.
+    ///
+    /// Writes `steps + 1` values including both ends and waits `step_delay` between the writes, with
+    /// `steps == 0` only `to` is written. The sweep stops at the first failed write.
+    ///
+    /// # Errors
+    ///
+    /// * If `from` or `to` is not between 0 and 1023, the function will return a B15FCommandError::ValueOutOfRange
+    ///   before anything is written.
+    /// * If a write fails, the function will return a B15FCommandError::StepFailed with the step and the error.
+    pub fn analog_ramp(
+        &mut self,
+        port: Port,
+        from: u16,
+        to: u16,
+        steps: u16,
+        step_delay: Duration,
+    ) -> Result<(), B15FCommandError> {
+        check_range(from, 1023)?;
+        check_range(to, 1023)?;
+        for step in 0..=steps {
+            if step > 0 {
+                std::thread::sleep(step_delay);
+            }
+            let value = if steps == 0 {
+                to
+            } else {
+                (from as i32 + (to as i32 - from as i32) * step as i32 / steps as i32) as u16
+            };
+            self.analog_write(port, value)
+                .map_err(|err| B15FCommandError::StepFailed {
+                    step,
+                    source: Box::new(err),
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Generates `cycles` periods of a waveform between 0 and `amplitude` on an analog output, e.g.
+    /// to feed a known signal back into an analog input.
+    ///
+    /// The signal is produced with timed [`B15F::analog_write`] calls, so it works with any firmware
+    /// but is limited by the serial round trip of every write (typically around a millisecond, see
+    /// [`Metrics`]). Writes happen as fast as possible, each one with the value of the waveform at
+    /// that time, so a period of 10 ms has only about 10 points and squares above a few hundred Hz
+    /// degrade to random pulses. The output keeps the last written value afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * If `amplitude` is not between 0 and 1023, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * Same as [`B15F::analog_write`] otherwise, the signal stops at the first error.
+    pub fn analog_waveform(
+        &mut self,
+        port: Port,
+        wave: Waveform,
+        amplitude: u16,
+        period: Duration,
+        cycles: u32,
+    ) -> Result<(), B15FCommandError> {
+        check_range(amplitude, 1023)?;
+        if period.is_zero() {
+            return Ok(());
+        }
+        let total = period.saturating_mul(cycles);
+        let started = Instant::now();
+        loop {
+            let elapsed = started.elapsed();
+            if elapsed >= total {
+                return Ok(());
+            }
+            let phase = (elapsed.as_secs_f32() / period.as_secs_f32()).fract();
+            self.analog_write(port, wave.value(phase, amplitude))?;
+        }
+    }
+
+    /// Writes both analog outputs back to back, e.g. for differential or quadrature signals.
+    ///
+    /// Both values are validated before anything is sent and both requests are flushed together,
+    /// so the outputs update as close together as the board allows.
+    ///
+    /// # Errors
+    ///
+    /// * If a value is not between 0 and 1023, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If any response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If any response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn analog_write_all(&mut self, value0: u16, value1: u16) -> Result<(), B15FCommandError> {
+        self.execute(Command::AnalogWriteAll { value0, value1 })?;
+        Ok(())
+    }
+
+    /// Sets all digital and analog outputs at once, e.g. to initialize an experiment.
+    ///
+    /// The values are validated before anything is sent, then all writes are sent back to back and
+    /// the responses are read afterwards. This keeps the time the outputs spend in a mixed old/new
+    /// state as short as possible.
+    ///
+    /// # Errors
+    ///
+    /// * If an analog value is not between 0 and 1023, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If any response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If any response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn configure_outputs(&mut self, config: &OutputConfig) -> Result<(), B15FCommandError> {
+        self.execute(Command::ConfigureOutputs(*config))?;
+        Ok(())
+    }
+
+    /// Sets the curve used by [`B15F::analog_write_percent`] for the given analog output.
+    ///
+    /// Actuators like LEDs or heaters don't respond linearly to the DAC value, with a matching curve
+    /// a linear percentage results in a linear perceived intensity. The default is [`OutputCurve::Linear`].
+    ///
+    /// # Errors
+    ///
+    /// * If a gamma exponent is negative or not finite, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If a table is empty or contains values above 1023, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set_output_curve(
+        &mut self,
+        port: Port,
+        curve: OutputCurve,
+    ) -> Result<(), B15FCommandError> {
+        curve.validate()?;
+        self.output_curves[port.index()] = curve;
+        Ok(())
+    }
+
+    /// Returns the curve currently used for the given analog output.
+    pub fn output_curve(&self, port: Port) -> &OutputCurve {
+        &self.output_curves[port.index()]
+    }
+
+    /// Writes an analog output as a percentage (0.0 - 100.0) mapped through its [`OutputCurve`].
+    ///
+    /// # Errors
+    ///
+    /// * If the percentage is not between 0.0 and 100.0, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * Same as [`B15F::analog_write`] otherwise.
+    pub fn analog_write_percent(
+        &mut self,
+        port: Port,
+        percent: f32,
+    ) -> Result<(), B15FCommandError> {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(B15FCommandError::ValueOutOfRange {
+                value: percent.into(),
+                min: 0.0,
+                max: 100.0,
+            });
+        }
+        let value = self.output_curves[port.index()].apply(percent / 100.0);
+        self.analog_write(port, value)
+    }
+
+    /// This is an experimental function sending multiple read requests to the board before reading the response.
+    /// It slightly reduces the latency compared to sending a single request per port.
+    /// Depending on the b15 implementation, it may not work as expected (my b32 experimental board works fine).
+    ///
+    /// The firmware revision is read with [`B15F::board_info`] and cached when the board is opened
+    /// with [`B15F::builder`]. Boards opened any other way, or whose revision couldn't be read while
+    /// opening, read it on the first call instead, which adds that round trip to it. Firmware reporting a revision older than 1.0 is
+    /// rejected with B15FCommandError::UnsupportedByFirmware instead of desyncing the connection,
+    /// firmware without a parseable revision is trusted.
+    ///
+    /// # Errors
+    ///
+    /// * If a requested analog input is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel before the reads are sent.
+    #[cfg(feature = "experimental")]
+    pub fn experiment_read_many(
+        &mut self,
+        ports: ReadManyPorts,
+    ) -> Result<([u8; 2], [u16; 8]), B15FCommandError> {
+        let version = self.cached_board_info()?.version();
+        if version.is_some_and(|version| version < READ_MANY_MIN_FIRMWARE) {
+            return Err(B15FCommandError::UnsupportedByFirmware("pipelined reads"));
+        }
+        let started = Instant::now();
+        let result = self.read_many(ports);
+        self.metrics.record(started.elapsed(), &result);
+        result
+    }
+
+    #[cfg(feature = "experimental")]
+    fn read_many(&mut self, ports: ReadManyPorts) -> Result<([u8; 2], [u16; 8]), B15FCommandError> {
+        let channels: Vec<u8> = (0..8)
+            .filter(|port| ports.contains(ReadManyPorts::from_analog(*port)))
+            .collect();
+        for &port in &channels {
+            self.check_channel(port)?;
+        }
+        if ports.contains(ReadManyPorts::Digital0) {
+            self.send_digital_read_request(Port::Port0)?;
+        }
+        if ports.contains(ReadManyPorts::Digital1) {
+            self.send_digital_read_request(Port::Port1)?;
+        }
+        for &port in &channels {
+            self.send_analog_read_request(port)?;
+        }
+        self.port_mut().flush()?;
+        self.check_input_buffer()?;
+
+        let mut digital = [0; 2];
+        let mut analog = [0; 8];
+
+        if ports.contains(ReadManyPorts::Digital0) {
+            digital[0] = self.read_digital_response()?;
+        }
+        if ports.contains(ReadManyPorts::Digital1) {
+            digital[1] = self.read_digital_response()?;
+        }
+        for &port in &channels {
+            analog[port as usize] = self.read_analog_response()?;
+        }
+
+        Ok((digital, analog))
+    }
+
+    /// Returns the number of analog inputs of the board.
+    ///
+    /// The firmware does not report its channel count, so this is 8 (the standard board) unless
+    /// it was changed with [`B15F::set_analog_channels`].
+    pub fn analog_channels(&self) -> u8 {
+        self.analog_channels
+    }
+
+    /// Sets the number of analog inputs for board variants with fewer or more than 8 channels.
+    /// Channel indices passed to the read methods are validated against this count.
+    ///
+    /// # Errors
+    ///
+    /// * If the count is zero, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set_analog_channels(&mut self, channels: u8) -> Result<(), B15FCommandError> {
+        check_analog_channels(channels)?;
+        self.analog_channels = channels;
+        Ok(())
+    }
+
+    /// Returns the ADC reference voltage used by [`B15F::analog_read_voltage`], 5.0 V by default.
+    pub fn analog_reference(&self) -> f32 {
+        self.analog_reference
+    }
+
+    /// Sets the ADC reference voltage, e.g. if the board runs with an external reference.
+    ///
+    /// # Errors
+    ///
+    /// * If the voltage is not finite and positive, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set_analog_reference(&mut self, voltage: f32) -> Result<(), B15FCommandError> {
+        check_analog_reference(voltage)?;
+        self.analog_reference = voltage;
+        Ok(())
+    }
+
+    fn check_channel(&self, channel: u8) -> Result<(), B15FCommandError> {
+        if channel < self.analog_channels {
+            Ok(())
+        } else {
+            Err(B15FCommandError::InvalidChannel {
+                channel,
+                valid: 0..=self.analog_channels.saturating_sub(1),
+            })
+        }
+    }
+
+    /// Reads the analog value from a specified port.
+    ///
+    /// This function sends a request to the specified analog port to read its current value.
+    /// The port number must be below [`B15F::analog_channels`] (0 to 7 on the standard board), otherwise, the function will return an error.
+    /// The function writes the request to the port, flushes the port to ensure the request is sent,
+    /// then reads the response from the port.
+    /// The response is a two-byte value, which is then converted to a u16 using little-endian byte order.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - A u8 representing the port number to read from. Must be below [`B15F::analog_channels`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u16, B15FCommandError>` - On success, returns the read value as a u16. On failure, returns a B15FCommandError.
+    ///
+    /// # Errors
+    ///
+    /// * If the port number is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the board answers with a value above 1023, the function will return a B15FCommandError::B15FError.
+    pub fn analog_read(&mut self, port: u8) -> Result<u16, B15FCommandError> {
+        execute_as!(
+            self,
+            Command::AnalogRead { channel: port },
+            CommandResult::Analog
+        )
+    }
+
+    /// Reads an analog input `samples` times and returns the median, for noisy lines or inputs.
+    ///
+    /// The protocol has no checksums, a corrupted response is only detected if it is out of the
+    /// ADC range. Such readings are skipped. Of the remaining ones, readings deviating from their
+    /// median by more than 32 counts (about 3% of the range) are rejected as outliers and the median
+    /// of the rest is returned. If more than half of the readings were skipped or rejected, the
+    /// input doesn't settle on a value and the reading fails.
+    ///
+    /// # Errors
+    ///
+    /// * If fewer than half of the readings are valid and close to the median, the function will return a B15FCommandError::B15FError.
+    /// * Otherwise same as [`B15F::analog_read`].
+    pub fn analog_read_checked(&mut self, port: u8, samples: u8) -> Result<u16, B15FCommandError> {
+        let samples = samples.max(1) as usize;
+        let mut values = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            match self.analog_read(port) {
+                Ok(value) => values.push(value),
+                Err(B15FCommandError::B15FError) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        values.sort_unstable();
+        let median = *values
+            .get(values.len() / 2)
+            .ok_or(B15FCommandError::B15FError)?;
+        values.retain(|value| value.abs_diff(median) <= ANALOG_OUTLIER_THRESHOLD);
+        if values.len() * 2 < samples {
+            return Err(B15FCommandError::B15FError);
+        }
+        Ok(values[values.len() / 2])
+    }
+
+    /// Reads an analog input and converts it to volts using [`B15F::analog_reference`].
     ///
-    /// # Arguments
+    /// # Errors
     ///
-    /// * `port` - A u8 representing the port number to read from. Must be between 0 and 7.
+    /// * Same as [`B15F::analog_read`].
+    pub fn analog_read_voltage(&mut self, port: u8) -> Result<f32, B15FCommandError> {
+        let value = self.analog_read(port)?;
+        Ok(value as f32 * self.analog_reference / 1023.0)
+    }
+
+    /// Reads two analog inputs of a differential pair and returns `pos - neg`, in -1023..=1023.
     ///
-    /// # Returns
+    /// Both channels are read with a single round trip like [`B15F::read_analog_batch`], so the
+    /// time between both samples is short but not zero.
     ///
-    /// * `Result<u16, B15FCommandError>` - On success, returns the read value as a u16. On failure, returns a B15FCommandError.
+    /// # Errors
     ///
-    /// # Panics
+    /// * If a channel is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel before anything is sent.
+    /// * Otherwise same as [`B15F::read_analog_batch`].
+    pub fn analog_read_differential(&mut self, pos: u8, neg: u8) -> Result<i16, B15FCommandError> {
+        let values = self.read_analog_batch(&[pos, neg])?;
+        Ok(values[0] as i16 - values[1] as i16)
+    }
+
+    /// Reads several analog inputs at once, in the given order (channels may repeat).
     ///
-    /// * If the port number is not between 0 and 7, the function will panic.
+    /// All requests are sent before the first response is read, which saves most of the round
+    /// trips compared to calling [`B15F::analog_read`] for every channel.
     ///
     /// # Errors
     ///
+    /// * If a channel is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel before anything is sent.
     /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
-    pub fn analog_read(&mut self, port: u8) -> Result<u16, B15FCommandError> {
-        self.send_analog_read_request(port)?;
-        self.read_analog_response()
+    /// * If the board answers with a value above 1023, the function will return a B15FCommandError::B15FError.
+    pub fn read_analog_batch(&mut self, channels: &[u8]) -> Result<Vec<u16>, B15FCommandError> {
+        let mut request = Vec::with_capacity(channels.len() * 2);
+        for &channel in channels {
+            self.check_channel(channel)?;
+            request.extend(proto::encode_analog_read(channel));
+        }
+        self.with_retries(true, |board| {
+            let mut response = vec![0u8; channels.len() * 2];
+            board.transact(&request, &mut response)?;
+            response.chunks_exact(2).map(proto::decode_analog).collect()
+        })
+    }
+
+    /// Like [`B15F::read_analog_batch`] but keeps the values read before a failure.
+    ///
+    /// Returns every channel with its value or error, in the given order. An invalid channel or a
+    /// garbage value only fails its own entry. After an IO error or a timeout the remaining responses
+    /// can't be told apart, so the list ends with the failed channel and the input buffer is
+    /// flushed.
+    ///
+    /// # Errors
+    ///
+    /// * If the requests can't be sent, the function will return a B15FCommandError::IoError.
+    pub fn read_analog_batch_partial(
+        &mut self,
+        channels: &[u8],
+    ) -> Result<Vec<ChannelReading>, B15FCommandError> {
+        let mut request = Vec::with_capacity(channels.len() * 2);
+        for &channel in channels {
+            if self.check_channel(channel).is_ok() {
+                request.extend(proto::encode_analog_read(channel));
+            }
+        }
+        let started = Instant::now();
+        if let Err(err) = self.write_request(&request) {
+            self.metrics.record_failure(&err);
+            return Err(err);
+        }
+        let mut values = Vec::with_capacity(channels.len());
+        let mut failed = false;
+        for &channel in channels {
+            if let Err(err) = self.check_channel(channel) {
+                values.push((channel, Err(err)));
+                continue;
+            }
+            match self.read_analog_response() {
+                Err(err @ (B15FCommandError::IoError(_) | B15FCommandError::Timeout(_))) => {
+                    self.metrics.record_failure(&err);
+                    values.push((channel, Err(err)));
+                    let _ = self.flush_input();
+                    return Ok(values);
+                }
+                value => {
+                    failed |= value.is_err();
+                    values.push((channel, value));
+                }
+            }
+        }
+        if failed {
+            self.metrics.record_failure(&B15FCommandError::B15FError);
+        } else {
+            self.metrics.record_success(started.elapsed());
+        }
+        Ok(values)
+    }
+
+    /// Reads the analog inputs 0 to 7 in one call, pipelined like [`B15F::read_analog_batch`].
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::read_analog_batch`], which includes B15FCommandError::InvalidChannel if
+    ///   [`B15F::analog_channels`] was set below 8.
+    pub fn analog_read_all(&mut self) -> Result<[u16; 8], B15FCommandError> {
+        let values = self.read_analog_batch(&[0, 1, 2, 3, 4, 5, 6, 7])?;
+        values.try_into().map_err(|_| B15FCommandError::B15FError)
+    }
+
+    /// Like [`B15F::analog_read_all`] but keeps the values read before a failure, see
+    /// [`B15F::read_analog_batch_partial`].
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::read_analog_batch_partial`].
+    pub fn analog_read_all_partial(&mut self) -> Result<Vec<ChannelReading>, B15FCommandError> {
+        self.read_analog_batch_partial(&[0, 1, 2, 3, 4, 5, 6, 7])
+    }
+
+    /// Reads two analog inputs back to back and returns `numerator / denominator`.
+    ///
+    /// Meant for ratiometric sensors (e.g. potentiometers) where the reference cancels out.
+    /// Both requests are sent before the responses are read, keeping the time between the two
+    /// samples as short as possible.
+    /// If the denominator reads zero, `f32::INFINITY` is returned (even if the numerator is zero too),
+    /// so the result is never NaN.
+    ///
+    /// # Errors
+    ///
+    /// * If a channel is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn analog_read_ratio(
+        &mut self,
+        numerator: u8,
+        denominator: u8,
+    ) -> Result<f32, B15FCommandError> {
+        self.check_channel(numerator)?;
+        self.check_channel(denominator)?;
+        let data = [
+            proto::encode_analog_read(numerator),
+            proto::encode_analog_read(denominator),
+        ]
+        .concat();
+        let (numerator, denominator) = self.with_retries(true, |board| {
+            let mut response = [0u8; 4];
+            board.transact(&data, &mut response)?;
+            Ok((
+                proto::decode_analog(&response[..2])?,
+                proto::decode_analog(&response[2..])?,
+            ))
+        })?;
+        if denominator == 0 {
+            return Ok(f32::INFINITY);
+        }
+        Ok(numerator as f32 / denominator as f32)
     }
 
     fn send_analog_read_request(&mut self, port: u8) -> Result<(), B15FCommandError> {
-        assert!(port <= 7, "analog read port must be between 0 and 7");
-        self.port
-            .write_all(&[RQ_ANALOG_READ, port])
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
-        Ok(())
+        self.check_channel(port)?;
+        self.write_request(&proto::encode_analog_read(port))
     }
 
     fn read_analog_response(&mut self) -> Result<u16, B15FCommandError> {
         let mut response = [0u8; 2];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        let response = u16::from_le_bytes(response);
-        Ok(response)
+        self.read_response(&mut response)?;
+        proto::decode_analog(&response)
     }
 
-    pub fn set_pwm_frequency(&mut self, frequency: f32) -> Result<u8, B15FCommandError> {
-        let data = frequency.to_le_bytes();
-        let data = [RQ_PWM_SET_FREQ, data[0], data[1], data[2], data[3]];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
+    /// Runs an ADC/DAC stroke and returns all samples, e.g. to measure a transfer function.
+    ///
+    /// The board drives DAC0 starting at `start` and adds `delta` after every step while sampling
+    /// the analog input `channel`, in a single board-side loop. Use [`B15F::adc_dac_stroke_iter`]
+    /// to process the samples while the stroke is still running.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::adc_dac_stroke_iter`], the first failed sample ends the stroke.
+    #[cfg(feature = "experimental")]
+    pub fn adc_dac_stroke(
+        &mut self,
+        channel: u8,
+        start: u16,
+        delta: i16,
+        count: u16,
+    ) -> Result<Vec<u16>, B15FCommandError> {
+        self.adc_dac_stroke_iter(channel, start, delta, count)?
+            .collect()
+    }
+
+    /// Starts an ADC/DAC stroke and returns an iterator reading the samples lazily.
+    ///
+    /// The board drives DAC0 starting at `start` and adds `delta` after every step while sampling
+    /// the analog input `channel`. This happens in a single board-side loop, so the sample density is
+    /// far higher than issuing `analog_write`/`analog_read` pairs over the serial line.
+    /// The samples are read from the port only when the iterator is advanced, so the first values are
+    /// available long before the whole stroke is finished.
+    ///
+    /// The iterator keeps the board borrowed mutably until it is dropped. If it is dropped before all
+    /// `count` samples were read, the remaining bytes are drained so the next command starts in sync.
+    /// If draining fails the input buffer is cleared instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - A u8 representing the analog input to sample. Must be below [`B15F::analog_channels`].
+    /// * `start` - The first value written to DAC0.
+    /// * `delta` - The value added to DAC0 after every sample.
+    /// * `count` - The number of samples to take.
+    ///
+    /// # Errors
+    ///
+    /// * If the channel is not below [`B15F::analog_channels`], the function will return a B15FCommandError::InvalidChannel.
+    /// * If there is an IO error when starting the stroke, the function will return a B15FCommandError::IoError.
+    /// * Every item may fail with a B15FCommandError, after that the iterator ends.
+    #[cfg(feature = "experimental")]
+    pub fn adc_dac_stroke_iter(
+        &mut self,
+        channel: u8,
+        start: u16,
+        delta: i16,
+        count: u16,
+    ) -> Result<AdcDacStrokeIter<'_, P>, B15FCommandError> {
+        self.check_channel(channel)?;
+        self.port()
+            .clear(ClearBuffer::Input)
+            .map_err(B15FCommandError::SerialPortError)?;
+        //the firmware samples two channels, we request the same one twice and drop the second value
+        let data = proto::encode_adc_dac_stroke(channel, channel, start, delta, count);
+        let started = Instant::now();
+        if let Err(err) = self.write_request(&data) {
+            self.metrics.record_failure(&err);
+            return Err(err);
+        }
+        Ok(AdcDacStrokeIter {
+            board: self,
+            remaining: count,
+            finished: false,
+            started,
+        })
+    }
 
+    #[cfg(feature = "experimental")]
+    fn read_stroke_sample(&mut self) -> Result<u16, B15FCommandError> {
+        self.check_input_buffer()?;
+        let mut response = [0u8; 4];
+        self.read_response(&mut response)?;
+        let (value, _) = proto::decode_stroke_sample(&response)?;
+        Ok(value)
+    }
+
+    #[cfg(feature = "experimental")]
+    fn read_stroke_end(&mut self) -> Result<(), B15FCommandError> {
         let mut response = [0u8];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
+        self.read_response(&mut response)?;
+        proto::decode_status(&response)
+    }
 
-        let response = response[0];
-        Ok(response)
+    /// Sets the start value of the board's hardware counter, e.g. to seed timing experiments.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If the response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn set_counter_offset(&mut self, offset: u16) -> Result<(), B15FCommandError> {
+        self.execute(Command::SetCounterOffset { offset })?;
+        Ok(())
+    }
+
+    /// Sets the PWM frequency in Hz and returns the timer top value the board chose.
+    ///
+    /// The frequency is rounded to whole Hz and sent as a 32 bit integer. The firmware picks the
+    /// smallest prescaler `p` of 1, 8, 64, 256 and 1024 for which `20 MHz / (frequency * p)` fits
+    /// into the 8 bit timer and sets top to that quotient minus one. The timer counts from 0 to top,
+    /// so the duty cycle is `value / top` (see [`B15F::set_pwm_value`]).
+    /// The achieved frequency is rounded, see [`B15F::set_pwm_frequency_actual`].
+    /// A frequency of 0 stops the timer and with it the PWM output, see [`B15F::pwm_disable`].
+    ///
+    /// # Errors
+    ///
+    /// * If the frequency is not 0 and not between [`pwm_min_frequency`] and [`pwm_max_frequency`],
+    ///   the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set_pwm_frequency(&mut self, frequency: f32) -> Result<u8, B15FCommandError> {
+        execute_as!(
+            self,
+            Command::PwmSetFrequency { frequency },
+            CommandResult::PwmTop
+        )
+    }
+
+    /// Sets the PWM frequency like [`B15F::set_pwm_frequency`] and returns the frequency the board
+    /// actually generates, which differs from the requested one by the rounding of the timer.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::set_pwm_frequency`].
+    pub fn set_pwm_frequency_actual(&mut self, frequency: f32) -> Result<f32, B15FCommandError> {
+        let top = self.set_pwm_frequency(frequency)?;
+        Ok(pwm_actual_frequency(frequency, top))
+    }
+
+    /// Stops the PWM output by setting the frequency to 0.
+    ///
+    /// Unlike a PWM value of 0, which still runs the timer, this leaves the pin in a defined off
+    /// state. [`B15F::reconnect`] disables PWM again if it was disabled before.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::set_pwm_frequency`].
+    pub fn pwm_disable(&mut self) -> Result<(), B15FCommandError> {
+        self.execute(Command::PwmDisable)?;
+        Ok(())
     }
 
+    #[deprecated(note = "use set_pwm_value")]
     pub fn set_pwm_vale(&mut self, value: u8) -> Result<(), B15FCommandError> {
-        let data = [RQ_PWM_SET_VALUE, value];
-        self.port
-            .write_all(&data)
-            .map_err(B15FCommandError::IoError)?;
-        self.port.flush().map_err(B15FCommandError::IoError)?;
-        let mut response = [0u8];
-        self.port
-            .read_exact(&mut response)
-            .map_err(B15FCommandError::IoError)?;
-        let response = response[0];
-        if response == MSG_OK {
-            Ok(())
+        self.set_pwm_value(value)
+    }
+
+    pub fn set_pwm_value(&mut self, value: u8) -> Result<(), B15FCommandError> {
+        self.execute(Command::PwmSetValue { value })?;
+        Ok(())
+    }
+
+    /// Returns the current PWM configuration.
+    ///
+    /// The firmware can't report its PWM state, so these are the values of the last successful
+    /// `set_pwm_frequency`/`set_pwm_value` calls of this handle ([`PwmConfig::confirmed`] is `false`).
+    /// Changes made by anything else (e.g. a board reset) are not reflected.
+    pub fn pwm_config(&mut self) -> Result<PwmConfig, B15FCommandError> {
+        Ok(self.pwm)
+    }
+
+    /// Sends raw request bytes and reads exactly `response_len` bytes back, to experiment with
+    /// firmware requests this crate doesn't wrap.
+    ///
+    /// The response is returned as is, checking for MSG_OK is up to the caller. A wrong
+    /// `response_len` desyncs the following commands, clear the input buffer if in doubt.
+    ///
+    /// # Errors
+    ///
+    /// * If fewer bytes came back within the timeout, the function will return a B15FCommandError::Timeout.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    pub fn raw_command(
+        &mut self,
+        request: &[u8],
+        response_len: usize,
+    ) -> Result<Vec<u8>, B15FCommandError> {
+        let mut response = vec![0u8; response_len];
+        self.transact(request, &mut response)?;
+        Ok(response)
+    }
+
+    /// Tests whether the firmware supports a request without parameters.
+    ///
+    /// Sends `request` with a short timeout and returns `true` if exactly `response_len` bytes came
+    /// back. Unknown requests either time out or answer with something else, which returns `false`.
+    /// Regardless of the outcome, the input buffer is cleared and the timeout is restored afterwards,
+    /// so the port is ready for the next command.
+    ///
+    /// Only probe requests which have no side effects, the board executes supported ones.
+    ///
+    /// # Errors
+    ///
+    /// * If the timeout can't be changed or the buffer can't be cleared, the function will return a B15FCommandError::SerialPortError.
+    /// * If there is an IO error other than a timeout, the function will return a B15FCommandError::IoError.
+    pub fn probe_command(
+        &mut self,
+        request: u8,
+        response_len: usize,
+    ) -> Result<bool, B15FCommandError> {
+        let timeout = self.port().timeout();
+        self.port_mut().set_timeout(PROBE_TIMEOUT)?;
+        let read_timeout = self.read_timeout.take();
+        let result = self.probe_request(request, response_len);
+        self.read_timeout = read_timeout;
+        let cleared = self.port().clear(ClearBuffer::Input);
+        let restored = self.port_mut().set_timeout(timeout);
+        let supported = result?;
+        cleared?;
+        restored?;
+        Ok(supported)
+    }
+
+    fn probe_request(
+        &mut self,
+        request: u8,
+        response_len: usize,
+    ) -> Result<bool, B15FCommandError> {
+        self.port().clear(ClearBuffer::Input)?;
+        self.write_request(&[request])?;
+        let mut response = vec![0u8; response_len];
+        match self.read_response(&mut response) {
+            Ok(()) => {}
+            Err(B15FCommandError::Timeout(_)) => return Ok(false),
+            Err(B15FCommandError::IoError(err))
+                if err.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                return Ok(false)
+            }
+            Err(err) => return Err(err),
+        }
+        //give a longer answer the chance to show up
+        std::thread::sleep(PROBE_TIMEOUT / 4);
+        Ok(self.port().bytes_to_read()? == 0)
+    }
+
+    /// Returns a handle to the servo output which has to be enabled before a position can be set.
+    pub fn servo(&mut self) -> ServoDisabled<'_, P> {
+        ServoDisabled::new(self)
+    }
+
+    /// Returns a handle to the PWM output which has to get a frequency before a value can be set.
+    pub fn pwm(&mut self) -> PwmUnconfigured<'_, P> {
+        PwmUnconfigured::new(self)
+    }
+
+    /// Enables the servo output, see [`B15F::servo`] for a handle that enforces enabling first.
+    ///
+    /// # Errors
+    ///
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If the response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn servo_enable(&mut self) -> Result<(), B15FCommandError> {
+        self.execute(Command::ServoEnable)?;
+        Ok(())
+    }
+
+    /// Disables the servo output.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::servo_enable`].
+    pub fn servo_disable(&mut self) -> Result<(), B15FCommandError> {
+        self.execute(Command::ServoDisable)?;
+        Ok(())
+    }
+
+    /// Sets the servo pulse length in microseconds, hobby servos usually expect 1000 - 2000.
+    ///
+    /// # Errors
+    ///
+    /// * If the pulse length is greater than 19000, the function will return a B15FCommandError::ValueOutOfRange.
+    /// * If there is an IO error when writing to or reading from the port, the function will return a B15FCommandError::IoError.
+    /// * If the response from the port is MSG_ERROR, the function will return a B15FCommandError::BoardRejected.
+    /// * If the response from the port is neither MSG_OK nor MSG_ERROR, the function will return a B15FCommandError::B15FError.
+    pub fn servo_set_position(&mut self, pulse: u16) -> Result<(), B15FCommandError> {
+        self.execute(Command::ServoSetPosition { pulse })?;
+        Ok(())
+    }
+}
+
+impl<P> Drop for B15F<P>
+where
+    P: serialport::SerialPort,
+{
+    fn drop(&mut self) {
+        if !self.reset_on_drop || self.port.is_none() {
+            return;
+        }
+        //don't block the exit for long if the board is gone
+        let _ = self.set_timeout(PROBE_TIMEOUT);
+        self.read_timeout = None;
+        self.retry = RetryPolicy::default();
+        let _ = self.analog_write_all(0, 0);
+        let _ = self.set_pwm_value(0);
+    }
+}
+
+/// Lazily reads the samples of an ADC/DAC stroke, see [`B15F::adc_dac_stroke_iter`].
+#[cfg(feature = "experimental")]
+pub struct AdcDacStrokeIter<'a, P>
+where
+    P: serialport::SerialPort,
+{
+    board: &'a mut B15F<P>,
+    remaining: u16,
+    finished: bool,
+    started: Instant,
+}
+
+#[cfg(feature = "experimental")]
+impl<P> AdcDacStrokeIter<'_, P>
+where
+    P: serialport::SerialPort,
+{
+    fn fail(&mut self, err: B15FCommandError) -> Option<Result<u16, B15FCommandError>> {
+        self.finished = true;
+        self.board.metrics.record_failure(&err);
+        //we don't know how many bytes are still in flight, at least drop what is already there
+        let _ = self.board.port().clear(ClearBuffer::Input);
+        Some(Err(err))
+    }
+}
+
+#[cfg(feature = "experimental")]
+impl<P> Iterator for AdcDacStrokeIter<'_, P>
+where
+    P: serialport::SerialPort,
+{
+    type Item = Result<u16, B15FCommandError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if self.remaining == 0 {
+            self.finished = true;
+            return match self.board.read_stroke_end() {
+                Ok(()) => {
+                    self.board.metrics.record_success(self.started.elapsed());
+                    None
+                }
+                Err(err) => self.fail(err),
+            };
+        }
+        match self.board.read_stroke_sample() {
+            Ok(value) => {
+                self.remaining -= 1;
+                Some(Ok(value))
+            }
+            Err(err) => self.fail(err),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.finished {
+            (0, Some(0))
         } else {
-            Err(B15FCommandError::B15FError)
+            (0, Some(self.remaining as usize + 1))
+        }
+    }
+}
+
+#[cfg(feature = "experimental")]
+impl<P> Drop for AdcDacStrokeIter<'_, P>
+where
+    P: serialport::SerialPort,
+{
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        #[cfg(feature = "log")]
+        debug!(
+            "[Stroke] Dropped early, draining {} samples",
+            self.remaining
+        );
+        let drained = (0..self.remaining)
+            .try_for_each(|_| self.board.read_stroke_sample().map(|_| ()))
+            .and_then(|_| self.board.read_stroke_end());
+        self.board.metrics.record(self.started.elapsed(), &drained);
+        if drained.is_err() {
+            let _ = self.board.port().clear(ClearBuffer::Input);
+        }
+    }
+}
+
+fn check_range(value: u16, max: u16) -> Result<(), B15FCommandError> {
+    if value > max {
+        Err(B15FCommandError::ValueOutOfRange {
+            value: value.into(),
+            min: 0.0,
+            max: max.into(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_analog_channels(channels: u8) -> Result<(), B15FCommandError> {
+    if channels == 0 {
+        Err(B15FCommandError::ValueOutOfRange {
+            value: 0.0,
+            min: 1.0,
+            max: u8::MAX.into(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn check_analog_reference(voltage: f32) -> Result<(), B15FCommandError> {
+    if voltage.is_finite() && voltage > 0.0 {
+        Ok(())
+    } else {
+        Err(B15FCommandError::ValueOutOfRange {
+            value: voltage.into(),
+            min: 0.0,
+            max: f32::MAX.into(),
+        })
+    }
+}
+
+/// Repeats the prescaler selection of the firmware to calculate the frequency for a timer top value.
+fn pwm_actual_frequency(requested: f32, top: u8) -> f32 {
+    let requested = requested.round() as u32;
+    if requested == 0 {
+        return 0.0;
+    }
+    let prescaler = PWM_PRESCALERS
+        .into_iter()
+        .find(|prescaler| CPU_FREQUENCY / requested.saturating_mul(*prescaler) <= 256)
+        .unwrap_or(PWM_PRESCALERS[PWM_PRESCALERS.len() - 1]);
+    CPU_FREQUENCY as f32 / (prescaler * (top as u32 + 1)) as f32
+}
+
+/// Tells a missing permission apart from other errors when opening a port.
+fn open_error(port_name: &str, err: serialport::Error) -> B15FInitError {
+    match err.kind() {
+        serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => {
+            B15FInitError::PermissionDenied {
+                port: port_name.to_string(),
+            }
         }
+        _ => B15FInitError::SerialPortError(err),
+    }
+}
+
+fn with_bit(byte: u8, bit: u8, value: bool) -> u8 {
+    if value {
+        byte | (1 << bit)
+    } else {
+        byte & !(1 << bit)
+    }
+}
+
+/// Whether a port may have a board attached judging by its metadata alone, before writing to it.
+fn maybe_b15f(port: &serialport::SerialPortInfo) -> bool {
+    match &port.port_type {
+        SerialPortType::UsbPort(usb) => KNOWN_USB_IDS.contains(&(usb.vid, usb.pid)),
+        //no ids to check, only a test request can tell
+        _ => true,
     }
 }
 
@@ -484,3 +2535,110 @@ fn port_priority(port: &serialport::SerialPortInfo) -> u8 {
     );
     priority
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockPort;
+
+    fn self_test_answer(status: u8) -> Result<bool, B15FCommandError> {
+        let port = MockPort::new()
+            .expect_write(&[RQ_SELF_TEST])
+            .reply(&[status]);
+        B15F::with_port_unchecked(port).self_test()
+    }
+
+    #[test]
+    fn self_test_decodes_status() {
+        assert!(self_test_answer(MSG_OK).unwrap());
+        assert!(!self_test_answer(MSG_ERROR).unwrap());
+        assert!(matches!(
+            self_test_answer(0x42),
+            Err(B15FCommandError::B15FError)
+        ));
+    }
+
+    #[test]
+    fn port_number_round_trip() {
+        for port in [Port::Port0, Port::Port1] {
+            assert_eq!(Port::try_from(u8::from(port)).ok(), Some(port));
+        }
+        assert!(matches!(
+            Port::try_from(2),
+            Err(B15FCommandError::InvalidPort(2))
+        ));
+    }
+
+    #[test]
+    fn digital_write_read_round_trip() {
+        //the inputs are wired to the outputs in reverse bit order, so the board answers 0x80
+        let port = MockPort::new()
+            .expect_write(&[RQ_DIGITAL_WRITE_0, 0b0000_0001])
+            .reply(&[MSG_OK])
+            .expect_write(&[RQ_DIGITAL_READ_0])
+            .reply(&[0b1000_0000])
+            .expect_write(&[RQ_DIGITAL_READ_0])
+            .reply(&[0b1000_0000]);
+        let mut board = B15F::with_port_unchecked(port);
+        board.digital_write(Port::Port0, 0b0000_0001).unwrap();
+        assert_eq!(board.digital_read(Port::Port0).unwrap(), 0b0000_0001);
+        assert_eq!(board.digital_read_raw(Port::Port0).unwrap(), 0b1000_0000);
+    }
+
+    fn checked_read(values: &[u16]) -> Result<u16, B15FCommandError> {
+        let mut port = MockPort::new();
+        for value in values {
+            port = port
+                .expect_write(&[RQ_ANALOG_READ, 0])
+                .reply(&value.to_le_bytes());
+        }
+        B15F::with_port_unchecked(port).analog_read_checked(0, values.len() as u8)
+    }
+
+    #[test]
+    fn analog_read_checked_rejects_outliers() {
+        assert_eq!(checked_read(&[500, 501, 900, 499, 502]).unwrap(), 501);
+        //a value above 1023 is skipped, the rest still agrees
+        assert_eq!(checked_read(&[500, 0xFFFF, 500]).unwrap(), 500);
+        //no value is shared by at least half of the readings
+        assert!(matches!(
+            checked_read(&[0, 500, 1023]),
+            Err(B15FCommandError::B15FError)
+        ));
+    }
+
+    #[test]
+    fn timed_out_reads_are_timeouts() {
+        let err = std::io::Error::new(std::io::ErrorKind::TimedOut, "slow");
+        assert!(matches!(
+            B15FCommandError::from(err),
+            B15FCommandError::Timeout(_)
+        ));
+        let err = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "gone");
+        assert!(matches!(
+            B15FCommandError::from(err),
+            B15FCommandError::IoError(_)
+        ));
+
+        //the board never answers
+        let port = MockPort::new().expect_write(&[RQ_ANALOG_READ, 0]);
+        let mut board = B15F::with_port_unchecked(port);
+        assert!(matches!(
+            board.analog_read(0),
+            Err(B15FCommandError::Timeout(_))
+        ));
+    }
+
+    #[test]
+    fn split_frames_are_reassembled() {
+        let [low, high] = 517u16.to_le_bytes();
+        let port = MockPort::new()
+            .expect_write(&[RQ_ANALOG_READ, 0])
+            .reply(&[low])
+            .stall()
+            .reply(&[high]);
+        let mut board = B15F::with_port_unchecked(port);
+        board.set_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(board.analog_read(0).unwrap(), 517);
+    }
+}
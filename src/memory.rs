@@ -0,0 +1,45 @@
+//! Raw access to the memory of the board's microcontroller.
+//!
+//! **These requests can brick the board.** Writing the wrong address corrupts the running firmware
+//! or the I/O registers (e.g. reconfigures the UART so the board stops answering) and may damage
+//! connected hardware. They are only meant for firmware developers debugging the board and are
+//! therefore behind the `memory-unsafe` feature.
+
+use crate::command::execute_as;
+use crate::{B15FCommandError, Command, CommandResult, B15F};
+use serialport::SerialPort;
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Reads a byte of the board's memory. Reading I/O registers may have side effects.
+    pub fn peek8(&mut self, address: u16) -> Result<u8, B15FCommandError> {
+        execute_as!(self, Command::Peek8 { address }, CommandResult::Memory8)
+    }
+
+    /// Writes a byte of the board's memory, see the module docs before using this.
+    ///
+    /// # Errors
+    ///
+    /// * If the board doesn't echo the written value, the function will return a B15FCommandError::B15FError.
+    pub fn poke8(&mut self, address: u16, value: u8) -> Result<(), B15FCommandError> {
+        self.execute(Command::Poke8 { address, value })?;
+        Ok(())
+    }
+
+    /// Reads a little-endian 16-bit word of the board's memory. Reading I/O registers may have side effects.
+    pub fn peek16(&mut self, address: u16) -> Result<u16, B15FCommandError> {
+        execute_as!(self, Command::Peek16 { address }, CommandResult::Memory16)
+    }
+
+    /// Writes a little-endian 16-bit word of the board's memory, see the module docs before using this.
+    ///
+    /// # Errors
+    ///
+    /// * If the board doesn't echo the written value, the function will return a B15FCommandError::B15FError.
+    pub fn poke16(&mut self, address: u16, value: u16) -> Result<(), B15FCommandError> {
+        self.execute(Command::Poke16 { address, value })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::B15FCommandError;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets in seconds.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0,
+];
+
+/// Counters collected for the commands sent to a board, see [`crate::B15F::metrics`].
+///
+/// A pipelined batch (like `experiment_read_many`) or a whole ADC/DAC stroke counts as one command.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    /// The number of commands sent to the board.
+    pub commands: u64,
+    /// The number of failed commands by error type.
+    pub errors: BTreeMap<&'static str, u64>,
+    /// The number of commands that were sent again after a failure.
+    pub retries: u64,
+    /// How often the OS input buffer was nearly full when reading a response.
+    pub input_buffer_warnings: u64,
+    /// The round-trip duration of the successful commands.
+    pub latency: LatencyHistogram,
+}
+
+/// A histogram of command durations with fixed buckets from 1 ms to 5 s.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Adds a duration to its bucket, durations above 5 s only count towards the count and sum.
+    pub fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += duration;
+    }
+
+    /// The number of observed durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of all observed durations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// The mean of all observed durations, `None` if nothing was observed yet.
+    pub fn mean(&self) -> Option<Duration> {
+        if self.count == 0 {
+            None
+        } else {
+            let nanos = self.sum.as_nanos() / u128::from(self.count);
+            Some(Duration::from_nanos(nanos as u64))
+        }
+    }
+
+    /// Returns `(upper bound in seconds, cumulative count)` for every bucket.
+    pub fn buckets(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(self.buckets.iter())
+            .scan(0, |cumulative, (bound, count)| {
+                *cumulative += count;
+                Some((*bound, *cumulative))
+            })
+    }
+}
+
+impl Metrics {
+    pub(crate) fn record<T>(&mut self, elapsed: Duration, result: &Result<T, B15FCommandError>) {
+        match result {
+            Ok(_) => self.record_success(elapsed),
+            Err(err) => self.record_failure(err),
+        }
+    }
+
+    pub(crate) fn record_success(&mut self, elapsed: Duration) {
+        self.commands += 1;
+        self.latency.observe(elapsed);
+    }
+
+    pub(crate) fn record_failure(&mut self, err: &B15FCommandError) {
+        self.commands += 1;
+        *self.errors.entry(err.kind()).or_default() += 1;
+    }
+
+    /// Formats the counters in the Prometheus text exposition format.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn to_prometheus(&self) -> String {
+        use std::fmt::Write;
+
+        fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        }
+
+        let mut out = String::new();
+        header(
+            &mut out,
+            "b15f_commands_total",
+            "counter",
+            "Commands sent to the board.",
+        );
+        let _ = writeln!(out, "b15f_commands_total {}", self.commands);
+
+        header(
+            &mut out,
+            "b15f_errors_total",
+            "counter",
+            "Failed commands by error type.",
+        );
+        for (kind, count) in &self.errors {
+            let _ = writeln!(out, "b15f_errors_total{{type=\"{}\"}} {}", kind, count);
+        }
+
+        header(
+            &mut out,
+            "b15f_retries_total",
+            "counter",
+            "Commands sent again after a failure.",
+        );
+        let _ = writeln!(out, "b15f_retries_total {}", self.retries);
+
+        header(
+            &mut out,
+            "b15f_input_buffer_warnings_total",
+            "counter",
+            "Reads with a nearly full input buffer.",
+        );
+        let _ = writeln!(
+            out,
+            "b15f_input_buffer_warnings_total {}",
+            self.input_buffer_warnings
+        );
+
+        let name = "b15f_command_duration_seconds";
+        header(
+            &mut out,
+            name,
+            "histogram",
+            "Round-trip duration of successful commands.",
+        );
+        for (bound, count) in self.latency.buckets() {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, self.latency.count);
+        let _ = writeln!(out, "{}_sum {}", name, self.latency.sum.as_secs_f64());
+        let _ = writeln!(out, "{}_count {}", name, self.latency.count);
+        out
+    }
+}
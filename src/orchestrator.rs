@@ -0,0 +1,153 @@
+use crate::{B15FCommandError, Command, CommandResult, B15F};
+use serialport::SerialPort;
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use thiserror::Error;
+
+/// Identifies a board within an [`Orchestrator`], assigned in the order the boards were added.
+pub type BoardId = usize;
+
+type Reply = mpsc::Sender<Result<CommandResult, B15FCommandError>>;
+
+#[derive(Debug, Error)]
+pub enum OrchestratorError {
+    #[error("command error: {0}")]
+    CommandError(#[from] B15FCommandError),
+    #[error("board thread stopped")]
+    WorkerStopped,
+    #[error("unknown board {0}")]
+    UnknownBoard(BoardId),
+}
+
+struct Worker {
+    jobs: Option<mpsc::Sender<(Command, Reply)>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Drives several boards in parallel, each one owned by its own thread.
+///
+/// Commands sent to multiple boards are handed to all threads before waiting for any answer, so the
+/// boards execute them at (nearly) the same time. Every board fails on its own: an error or even a
+/// panic on one board only shows up in that board's result.
+/// Dropping the orchestrator stops the threads and closes the boards.
+#[derive(Default)]
+pub struct Orchestrator {
+    workers: Vec<Worker>,
+}
+
+impl Orchestrator {
+    pub fn new() -> Self {
+        Orchestrator::default()
+    }
+
+    /// Moves a board onto a new thread and returns its id.
+    pub fn add<P>(&mut self, mut board: B15F<P>) -> BoardId
+    where
+        P: SerialPort + 'static,
+    {
+        let (jobs, queue) = mpsc::channel::<(Command, Reply)>();
+        let thread = std::thread::spawn(move || {
+            for (command, reply) in queue {
+                let _ = reply.send(board.execute(command));
+            }
+        });
+        self.workers.push(Worker {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        });
+        self.workers.len() - 1
+    }
+
+    /// The number of boards.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workers.is_empty()
+    }
+
+    /// Executes a command on a single board.
+    pub fn execute(
+        &self,
+        board: BoardId,
+        command: Command,
+    ) -> Result<CommandResult, OrchestratorError> {
+        let worker = self
+            .workers
+            .get(board)
+            .ok_or(OrchestratorError::UnknownBoard(board))?;
+        let answer = Self::dispatch(worker, command)?;
+        Self::wait(answer)
+    }
+
+    /// Executes a command on every board and returns the results by board id.
+    pub fn broadcast(
+        &self,
+        command: Command,
+    ) -> BTreeMap<BoardId, Result<CommandResult, OrchestratorError>> {
+        let pending: Vec<_> = self
+            .workers
+            .iter()
+            .map(|worker| Self::dispatch(worker, command))
+            .collect();
+        pending
+            .into_iter()
+            .enumerate()
+            .map(|(id, answer)| (id, answer.and_then(Self::wait)))
+            .collect()
+    }
+
+    /// Reads one analog channel of every board and returns the values by board id.
+    pub fn gather(&self, channel: u8) -> BTreeMap<BoardId, Result<u16, OrchestratorError>> {
+        self.broadcast(Command::AnalogRead { channel })
+            .into_iter()
+            .map(|(id, result)| {
+                let value = result.map(|result| match result {
+                    CommandResult::Analog(value) => value,
+                    _ => unreachable!("analog read answered with {:?}", result),
+                });
+                (id, value)
+            })
+            .collect()
+    }
+
+    fn dispatch(
+        worker: &Worker,
+        command: Command,
+    ) -> Result<mpsc::Receiver<Result<CommandResult, B15FCommandError>>, OrchestratorError> {
+        let (reply, answer) = mpsc::channel();
+        worker
+            .jobs
+            .as_ref()
+            .ok_or(OrchestratorError::WorkerStopped)?
+            .send((command, reply))
+            .map_err(|_| OrchestratorError::WorkerStopped)?;
+        Ok(answer)
+    }
+
+    fn wait(
+        answer: mpsc::Receiver<Result<CommandResult, B15FCommandError>>,
+    ) -> Result<CommandResult, OrchestratorError> {
+        //the reply sender is dropped without an answer if the thread panicked
+        let result = answer
+            .recv()
+            .map_err(|_| OrchestratorError::WorkerStopped)?;
+        Ok(result?)
+    }
+}
+
+impl Drop for Orchestrator {
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            //closing the queue ends the thread loop
+            worker.jobs.take();
+        }
+        for worker in &mut self.workers {
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
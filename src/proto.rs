@@ -0,0 +1,368 @@
+//! Pure encoding of the requests and decoding of the board responses without any IO.
+//!
+//! Every encoder returns the request bytes, ready to be sent over any transport. Encoders don't
+//! validate their arguments, the range checks are up to the caller like in [`crate::B15F`].
+//! Every decoder takes the raw response bytes and validates them, so garbage from a wrong device
+//! or a desynced line becomes an error instead of a panic or a silently wrong value.
+//! The IO methods of [`crate::B15F`] use these functions, which also makes them easy to unit
+//! test and fuzz.
+
+#[cfg(feature = "experimental")]
+use crate::RQ_ADC_DAC_STROKE;
+use crate::{
+    B15FCommandError, BoardInfo, Port, MSG_ERROR, MSG_OK, RQ_ANALOG_READ, RQ_ANALOG_WRITE_0,
+    RQ_ANALOG_WRITE_1, RQ_COUNTER_OFFSET, RQ_DIGITAL_READ_0, RQ_DIGITAL_READ_1, RQ_DIGITAL_WRITE_0,
+    RQ_DIGITAL_WRITE_1, RQ_INT_TEST, RQ_PWM_SET_FREQ, RQ_PWM_SET_VALUE, RQ_SERVO_SET_POS, RQ_TEST,
+};
+#[cfg(feature = "memory-unsafe")]
+use crate::{RQ_GET_MEM_16, RQ_GET_MEM_8, RQ_SET_MEM_16, RQ_SET_MEM_8};
+
+/// Encodes a test request, the board echoes `value`.
+pub fn encode_test(value: u8) -> [u8; 2] {
+    [RQ_TEST, value]
+}
+
+/// Encodes an integer test, the board answers `value * 3`.
+pub fn encode_int_test(value: u16) -> [u8; 3] {
+    let [low, high] = value.to_le_bytes();
+    [RQ_INT_TEST, low, high]
+}
+
+/// Encodes a digital port write, `value` is sent as is (see [`crate::BitOrder`]).
+pub fn encode_digital_write(port: Port, value: u8) -> [u8; 2] {
+    match port {
+        Port::Port0 => [RQ_DIGITAL_WRITE_0, value],
+        Port::Port1 => [RQ_DIGITAL_WRITE_1, value],
+    }
+}
+
+/// Encodes a digital port read.
+pub fn encode_digital_read(port: Port) -> [u8; 1] {
+    match port {
+        Port::Port0 => [RQ_DIGITAL_READ_0],
+        Port::Port1 => [RQ_DIGITAL_READ_1],
+    }
+}
+
+/// Encodes an analog output write, `value` has to be between 0 and 1023.
+pub fn encode_analog_write(port: Port, value: u16) -> [u8; 3] {
+    let [low, high] = value.to_le_bytes();
+    match port {
+        Port::Port0 => [RQ_ANALOG_WRITE_0, low, high],
+        Port::Port1 => [RQ_ANALOG_WRITE_1, low, high],
+    }
+}
+
+/// Encodes an analog input read.
+pub fn encode_analog_read(channel: u8) -> [u8; 2] {
+    [RQ_ANALOG_READ, channel]
+}
+
+/// Encodes an ADC/DAC stroke sampling `channel_a` and `channel_b` for `count` DAC0 values.
+#[cfg(feature = "experimental")]
+pub fn encode_adc_dac_stroke(
+    channel_a: u8,
+    channel_b: u8,
+    start: u16,
+    delta: i16,
+    count: u16,
+) -> [u8; 9] {
+    let [start_low, start_high] = start.to_le_bytes();
+    let [delta_low, delta_high] = delta.to_le_bytes();
+    let [count_low, count_high] = count.to_le_bytes();
+    [
+        RQ_ADC_DAC_STROKE,
+        channel_a,
+        channel_b,
+        start_low,
+        start_high,
+        delta_low,
+        delta_high,
+        count_low,
+        count_high,
+    ]
+}
+
+/// Encodes a PWM frequency request, the frequency is in whole Hz.
+pub fn encode_pwm_frequency(frequency: u32) -> [u8; 5] {
+    let [b0, b1, b2, b3] = frequency.to_le_bytes();
+    [RQ_PWM_SET_FREQ, b0, b1, b2, b3]
+}
+
+/// Encodes a PWM value request.
+pub fn encode_pwm_value(value: u8) -> [u8; 2] {
+    [RQ_PWM_SET_VALUE, value]
+}
+
+/// Encodes a counter offset request.
+pub fn encode_counter_offset(offset: u16) -> [u8; 3] {
+    let [low, high] = offset.to_le_bytes();
+    [RQ_COUNTER_OFFSET, low, high]
+}
+
+/// Encodes a servo position request, `pulse` in microseconds up to 19000.
+pub fn encode_servo_position(pulse: u16) -> [u8; 3] {
+    let [low, high] = pulse.to_le_bytes();
+    [RQ_SERVO_SET_POS, low, high]
+}
+
+/// Encodes a read of the byte at `address`.
+#[cfg(feature = "memory-unsafe")]
+pub fn encode_get_mem_8(address: u16) -> [u8; 3] {
+    let [low, high] = address.to_le_bytes();
+    [RQ_GET_MEM_8, low, high]
+}
+
+/// Encodes a write of the byte at `address`, the board echoes `value`.
+#[cfg(feature = "memory-unsafe")]
+pub fn encode_set_mem_8(address: u16, value: u8) -> [u8; 4] {
+    let [low, high] = address.to_le_bytes();
+    [RQ_SET_MEM_8, low, high, value]
+}
+
+/// Encodes a read of the little-endian word at `address`.
+#[cfg(feature = "memory-unsafe")]
+pub fn encode_get_mem_16(address: u16) -> [u8; 3] {
+    let [low, high] = address.to_le_bytes();
+    [RQ_GET_MEM_16, low, high]
+}
+
+/// Encodes a write of the little-endian word at `address`, the board echoes `value`.
+#[cfg(feature = "memory-unsafe")]
+pub fn encode_set_mem_16(address: u16, value: u16) -> [u8; 5] {
+    let [low, high] = address.to_le_bytes();
+    let [value_low, value_high] = value.to_le_bytes();
+    [RQ_SET_MEM_16, low, high, value_low, value_high]
+}
+
+fn exact<const N: usize>(response: &[u8]) -> Result<[u8; N], B15FCommandError> {
+    response.try_into().map_err(|_| B15FCommandError::B15FError)
+}
+
+/// Decodes the single status byte most write requests answer with.
+pub fn decode_status(response: &[u8]) -> Result<(), B15FCommandError> {
+    let [status] = exact(response)?;
+    match status {
+        MSG_OK => Ok(()),
+        MSG_ERROR => Err(B15FCommandError::BoardRejected),
+        _ => Err(B15FCommandError::B15FError),
+    }
+}
+
+/// Decodes several status bytes of pipelined write requests, all of them have to be MSG_OK.
+pub fn decode_statuses(response: &[u8]) -> Result<(), B15FCommandError> {
+    response.chunks(1).try_for_each(decode_status)
+}
+
+/// Decodes the answer to a test request, returns whether the board echoed `expected`.
+pub fn decode_test(response: &[u8], expected: u8) -> Result<bool, B15FCommandError> {
+    Ok(decode_test_echo(response)? == expected)
+}
+
+/// Decodes the answer to a test request, returns the byte the board echoed.
+pub fn decode_test_echo(response: &[u8]) -> Result<u8, B15FCommandError> {
+    let [status, echo] = exact(response)?;
+    decode_status(&[status])?;
+    Ok(echo)
+}
+
+/// Decodes the answer to an integer test, returns whether the board answered `value * 3`.
+pub fn decode_int_test(response: &[u8], value: u16) -> Result<bool, B15FCommandError> {
+    let answer = u16::from_le_bytes(exact(response)?);
+    Ok(answer == value.wrapping_mul(3))
+}
+
+/// Decodes the answer to a self test, MSG_OK if it passed and MSG_ERROR if it failed.
+pub fn decode_self_test(response: &[u8]) -> Result<bool, B15FCommandError> {
+    match exact(response)? {
+        [MSG_OK] => Ok(true),
+        [MSG_ERROR] => Ok(false),
+        _ => Err(B15FCommandError::B15FError),
+    }
+}
+
+/// Decodes a digital port value, the board sends the bits in reverse order.
+pub fn decode_digital(response: &[u8]) -> Result<u8, B15FCommandError> {
+    Ok(decode_digital_raw(response)?.reverse_bits())
+}
+
+/// Decodes a digital port value as the board sends it, in reverse bit order.
+pub fn decode_digital_raw(response: &[u8]) -> Result<u8, B15FCommandError> {
+    let [value] = exact(response)?;
+    Ok(value)
+}
+
+/// Decodes the DIP switch positions, the board sends the bits in reverse order like the digital ports.
+pub fn decode_dip_switch(response: &[u8]) -> Result<u8, B15FCommandError> {
+    let [value] = exact(response)?;
+    Ok(value.reverse_bits())
+}
+
+/// Decodes a little-endian 10-bit ADC value, values above 1023 are rejected.
+pub fn decode_analog(response: &[u8]) -> Result<u16, B15FCommandError> {
+    let value = u16::from_le_bytes(exact(response)?);
+    if value > 1023 {
+        return Err(B15FCommandError::B15FError);
+    }
+    Ok(value)
+}
+
+/// Decodes one step of an ADC/DAC stroke, the ADC values of both sampled channels.
+pub fn decode_stroke_sample(response: &[u8]) -> Result<(u16, u16), B15FCommandError> {
+    let [a0, a1, b0, b1] = exact(response)?;
+    Ok((decode_analog(&[a0, a1])?, decode_analog(&[b0, b1])?))
+}
+
+/// Decodes the answer to a memory write, the board echoes the written value.
+pub fn decode_echo(response: &[u8], expected: &[u8]) -> Result<(), B15FCommandError> {
+    if response == expected {
+        Ok(())
+    } else {
+        Err(B15FCommandError::B15FError)
+    }
+}
+
+/// Decodes a string of the info response, the firmware terminates it with a NUL byte.
+pub fn decode_string(response: &[u8]) -> Result<String, B15FCommandError> {
+    let string = response.strip_suffix(&[0]).unwrap_or(response);
+    if !string.is_ascii() {
+        return Err(B15FCommandError::B15FError);
+    }
+    Ok(String::from_utf8_lossy(string).into_owned())
+}
+
+/// Decodes the complete answer to an info request.
+///
+/// The board answers with a count followed by that many length-prefixed strings (build date,
+/// build time and firmware revision) and a status byte. Missing or empty strings result in an
+/// empty `firmware` or no `build_date`, a truncated response or bytes after the status byte are
+/// rejected.
+pub fn decode_info(response: &[u8]) -> Result<BoardInfo, B15FCommandError> {
+    let (&count, mut rest) = response.split_first().ok_or(B15FCommandError::B15FError)?;
+    let mut strings = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (&len, tail) = rest.split_first().ok_or(B15FCommandError::B15FError)?;
+        if tail.len() < len as usize {
+            return Err(B15FCommandError::B15FError);
+        }
+        let (bytes, tail) = tail.split_at(len as usize);
+        strings.push(decode_string(bytes)?);
+        rest = tail;
+    }
+    decode_status(rest)?;
+
+    let mut strings = strings.into_iter();
+    let date = strings.next().filter(|date| !date.is_empty());
+    let time = strings.next().filter(|time| !time.is_empty());
+    Ok(BoardInfo {
+        firmware: strings.next().unwrap_or_default(),
+        build_date: date.map(|date| match time {
+            Some(time) => format!("{} {}", date, time),
+            None => date,
+        }),
+    })
+}
+
+/// Decodes the answer to a PWM frequency request, the timer top value.
+pub fn decode_pwm_top(response: &[u8]) -> Result<u8, B15FCommandError> {
+    let [top] = exact(response)?;
+    Ok(top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_garbage<T>(result: Result<T, B15FCommandError>) -> bool {
+        matches!(result, Err(B15FCommandError::B15FError))
+    }
+
+    #[test]
+    fn truncated_responses_are_rejected() {
+        assert!(is_garbage(decode_status(&[])));
+        assert!(is_garbage(decode_test_echo(&[MSG_OK])));
+        assert!(is_garbage(decode_digital(&[])));
+        assert!(is_garbage(decode_analog(&[0x12])));
+        assert!(is_garbage(decode_stroke_sample(&[0, 0, 0])));
+        assert!(is_garbage(decode_info(&[])));
+        //two strings announced, the second one and the status byte are missing
+        assert!(is_garbage(decode_info(&[2, 1, b'a'])));
+        //the string is shorter than its length byte
+        assert!(is_garbage(decode_info(&[1, 4, b'a', b'b'])));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(is_garbage(decode_status(&[0x42])));
+        assert!(is_garbage(decode_statuses(&[MSG_OK, 0x42])));
+        assert!(is_garbage(decode_self_test(&[0x42])));
+        assert!(is_garbage(decode_test_echo(&[0x42, 0x42])));
+        assert!(is_garbage(decode_analog(&[0, 0, 0])));
+        assert!(is_garbage(decode_string(&[b'a', 0xFF])));
+        assert!(is_garbage(decode_info(&[0, MSG_OK, 0x42])));
+    }
+
+    #[test]
+    fn msg_error_is_reported_as_rejected() {
+        assert!(matches!(
+            decode_status(&[MSG_ERROR]),
+            Err(B15FCommandError::BoardRejected)
+        ));
+        assert!(matches!(
+            decode_statuses(&[MSG_OK, MSG_ERROR]),
+            Err(B15FCommandError::BoardRejected)
+        ));
+        assert!(matches!(
+            decode_test_echo(&[MSG_ERROR, 0x42]),
+            Err(B15FCommandError::BoardRejected)
+        ));
+        assert!(matches!(
+            decode_info(&[0, MSG_ERROR]),
+            Err(B15FCommandError::BoardRejected)
+        ));
+        assert!(!decode_self_test(&[MSG_ERROR]).unwrap());
+    }
+
+    #[test]
+    fn analog_values_above_1023_are_rejected() {
+        assert_eq!(decode_analog(&1023u16.to_le_bytes()).unwrap(), 1023);
+        assert!(is_garbage(decode_analog(&1024u16.to_le_bytes())));
+        assert!(is_garbage(decode_analog(&[0xFF, 0xFF])));
+        assert!(is_garbage(decode_stroke_sample(&[0, 0, 0x00, 0x04])));
+    }
+
+    #[test]
+    #[cfg(feature = "memory-unsafe")]
+    fn memory_requests_are_little_endian() {
+        assert_eq!(encode_get_mem_8(0x1234), [RQ_GET_MEM_8, 0x34, 0x12]);
+        assert_eq!(
+            encode_set_mem_8(0x1234, 0xAB),
+            [RQ_SET_MEM_8, 0x34, 0x12, 0xAB]
+        );
+        assert_eq!(encode_get_mem_16(0x1234), [RQ_GET_MEM_16, 0x34, 0x12]);
+        assert_eq!(
+            encode_set_mem_16(0x1234, 0xABCD),
+            [RQ_SET_MEM_16, 0x34, 0x12, 0xCD, 0xAB]
+        );
+    }
+
+    #[test]
+    fn info_is_decoded() {
+        let response = [
+            &[3, 12][..],
+            b"Jan  1 1970\0",
+            &[9],
+            b"00:00:00\0",
+            &[4],
+            b"1.2\0",
+            &[MSG_OK],
+        ]
+        .concat();
+        let info = decode_info(&response).unwrap();
+        assert_eq!(info.firmware, "1.2");
+        assert_eq!(info.build_date.as_deref(), Some("Jan  1 1970 00:00:00"));
+
+        let info = decode_info(&[0, MSG_OK]).unwrap();
+        assert_eq!(info, BoardInfo::default());
+    }
+}
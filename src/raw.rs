@@ -0,0 +1,49 @@
+use crate::B15F;
+use serialport::SerialPort;
+use std::io;
+
+/// Raw byte access to the serial port of a board, see [`B15F::raw_io`].
+pub struct RawIo<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Uses the board as a plain byte stream, e.g. to tunnel data through extended firmware with a
+    /// UART passthrough or a custom sub-protocol.
+    ///
+    /// Bytes are read and written as they are, without the request framing, retries or metrics of
+    /// the protocol methods. The board is borrowed until the guard is dropped, so protocol methods
+    /// can't be interleaved with raw IO. Leave the firmware in its command mode and call
+    /// [`B15F::flush_input`] afterwards, leftovers would be read as the answer to the next command.
+    pub fn raw_io(&mut self) -> RawIo<'_, P> {
+        RawIo { board: self }
+    }
+}
+
+impl<P> io::Read for RawIo<'_, P>
+where
+    P: SerialPort,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.board.port_mut().read(buf)
+    }
+}
+
+impl<P> io::Write for RawIo<'_, P>
+where
+    P: SerialPort,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.board.port_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.board.port_mut().flush()
+    }
+}
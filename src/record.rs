@@ -0,0 +1,393 @@
+//! Recording and replaying serial sessions to debug a board offline.
+//!
+//! [`RecordingPort`] wraps any port and appends everything written to and read from it to a log.
+//! [`ReplayPort`] plays such a log back, so a session can be reproduced without the hardware.
+//!
+//! ```no_run
+//! use b15f::record::{RecordingPort, ReplayPort};
+//! use b15f::B15F;
+//!
+//! let port = serialport::new("/dev/ttyUSB0", 57600).open_native().unwrap();
+//! let port = RecordingPort::create(port, "session.b15f").unwrap();
+//! let mut board = B15F::from(port).unwrap();
+//! board.analog_read(0).unwrap();
+//! drop(board);
+//!
+//! let mut replay = B15F::from(ReplayPort::open("session.b15f").unwrap()).unwrap();
+//! replay.analog_read(0).unwrap();
+//! ```
+//!
+//! # Log format
+//! The log starts with the magic bytes `B15FREC` and a format version byte (currently 1),
+//! followed by frames of
+//! * a direction byte, `>` for bytes written to the board, `<` for bytes read from it,
+//! * the number of bytes as little endian `u32`,
+//! * the bytes themselves.
+
+use crate::fixed_port::fixed_serial_port;
+use crate::RQ_TEST;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, StopBits};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const MAGIC: &[u8; 7] = b"B15FREC";
+const VERSION: u8 = 1;
+const WRITTEN: u8 = b'>';
+const READ: u8 = b'<';
+
+/// A port recording all traffic of the wrapped port to a log, see the [module](self) docs.
+///
+/// Every frame is flushed right away, so the log is complete up to a crash or a hanging board.
+pub struct RecordingPort<P, W: Write = BufWriter<File>> {
+    inner: P,
+    log: W,
+}
+
+impl<P: serialport::SerialPort> RecordingPort<P, BufWriter<File>> {
+    /// Records the traffic of `inner` to a newly created file at `path`.
+    pub fn create(inner: P, path: impl AsRef<Path>) -> io::Result<Self> {
+        RecordingPort::new(inner, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<P: serialport::SerialPort, W: Write> RecordingPort<P, W> {
+    /// Records the traffic of `inner` to `log`, starting with the log header.
+    pub fn new(inner: P, mut log: W) -> io::Result<Self> {
+        log.write_all(MAGIC)?;
+        log.write_all(&[VERSION])?;
+        log.flush()?;
+        Ok(RecordingPort { inner, log })
+    }
+
+    /// Returns the wrapped port and the log.
+    pub fn into_inner(self) -> (P, W) {
+        (self.inner, self.log)
+    }
+
+    fn record(&mut self, direction: u8, bytes: &[u8]) -> io::Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.log.write_all(&[direction])?;
+        self.log.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        self.log.write_all(bytes)?;
+        self.log.flush()
+    }
+}
+
+impl<P: serialport::SerialPort, W: Write> Read for RecordingPort<P, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.record(READ, &buf[..len])?;
+        Ok(len)
+    }
+}
+
+impl<P: serialport::SerialPort, W: Write> Write for RecordingPort<P, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.record(WRITTEN, &buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<P: serialport::SerialPort, W: Write + Send> serialport::SerialPort for RecordingPort<P, W> {
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.inner.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.inner.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.inner.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.inner.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.inner.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.inner.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.inner.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.inner.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.inner.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.inner.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.inner.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.inner.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.inner.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        self.inner.clear(buffer_to_clear)
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn serialport::SerialPort>> {
+        Err(serialport::Error::new(
+            serialport::ErrorKind::Unknown,
+            "a recording port can't be cloned",
+        ))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.inner.set_break()
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.inner.clear_break()
+    }
+}
+
+#[derive(Debug)]
+enum Frame {
+    Written(Vec<u8>),
+    Read(Vec<u8>),
+}
+
+/// A port playing back a log written by [`RecordingPort`].
+///
+/// Recorded reads become readable once every write recorded before them was received, like the
+/// board answering a request. A write differing from the recording fails with
+/// [`io::ErrorKind::InvalidData`], reading past the recorded reads times out like a silent board.
+/// Test requests may carry a different random byte than recorded, the replayed answer echoes it.
+#[derive(Debug)]
+pub struct ReplayPort {
+    frames: VecDeque<Frame>,
+    written: Vec<u8>,
+    //position in the answer to a test request and the byte to echo
+    test_echo: Option<(usize, u8)>,
+    output: Mutex<VecDeque<u8>>,
+    timeout: Duration,
+}
+
+impl ReplayPort {
+    /// Loads the log at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        ReplayPort::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Loads a log from `reader`.
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..7] != MAGIC || header[7] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a b15f session recording",
+            ));
+        }
+        let mut frames = VecDeque::new();
+        let mut direction = [0];
+        while reader.read(&mut direction)? == 1 {
+            let mut len = [0; 4];
+            reader.read_exact(&mut len)?;
+            let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+            reader.read_exact(&mut bytes)?;
+            frames.push_back(match direction[0] {
+                WRITTEN => Frame::Written(bytes),
+                READ => Frame::Read(bytes),
+                other => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown frame direction {other:#04X} in session recording"),
+                    ))
+                }
+            });
+        }
+        let mut port = ReplayPort {
+            frames,
+            written: Vec::new(),
+            test_echo: None,
+            output: Mutex::new(VecDeque::new()),
+            timeout: Duration::ZERO,
+        };
+        port.advance()?;
+        Ok(port)
+    }
+
+    /// Returns whether the whole recording was played back.
+    pub fn is_done(&self) -> bool {
+        self.frames.is_empty() && self.written.is_empty()
+    }
+
+    fn output(&self) -> std::sync::MutexGuard<'_, VecDeque<u8>> {
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn advance(&mut self) -> io::Result<()> {
+        loop {
+            match self.frames.front_mut() {
+                Some(Frame::Read(bytes)) => {
+                    let mut bytes = std::mem::take(bytes);
+                    for byte in bytes.iter_mut() {
+                        match self.test_echo.take() {
+                            Some((0, echo)) => self.test_echo = Some((1, echo)),
+                            Some((_, echo)) => *byte = echo,
+                            None => break,
+                        }
+                    }
+                    self.output().extend(bytes);
+                }
+                Some(Frame::Written(expected)) => {
+                    if expected.len() == 2
+                        && expected[0] == RQ_TEST
+                        && self.written.len() >= 2
+                        && self.written[0] == RQ_TEST
+                    {
+                        expected[1] = self.written[1];
+                        self.test_echo = Some((0, self.written[1]));
+                    }
+                    let len = expected.len().min(self.written.len());
+                    if self.written[..len] != expected[..len] {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "write {:02X?} differs from the recorded {:02X?}",
+                                &self.written[..len],
+                                &expected[..len]
+                            ),
+                        ));
+                    }
+                    self.written.drain(..len);
+                    expected.drain(..len);
+                    if !expected.is_empty() {
+                        return Ok(());
+                    }
+                }
+                None if self.written.is_empty() => return Ok(()),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("write {:02X?} after the end of the recording", self.written),
+                    ))
+                }
+            }
+            self.frames.pop_front();
+        }
+    }
+}
+
+impl Write for ReplayPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        self.advance()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fixed_serial_port!(
+    ReplayPort,
+    "replay",
+    "a replay port can't be cloned",
+    "the recording has nothing more to send"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockPort;
+    use crate::{B15FCommandError, B15F, RQ_ANALOG_READ};
+
+    fn record_session() -> Vec<u8> {
+        let port = MockPort::new()
+            .expect_test()
+            .expect_write(&[RQ_ANALOG_READ, 3])
+            .reply(&517u16.to_le_bytes());
+        let mut board = B15F::from(RecordingPort::new(port, Vec::new()).unwrap()).unwrap();
+        assert_eq!(board.analog_read(3).unwrap(), 517);
+        let (port, log) = board.into_inner().into_inner();
+        assert!(port.is_done());
+        log
+    }
+
+    #[test]
+    fn recorded_session_replays() {
+        let log = record_session();
+        //the test request of the replay carries a different random byte than the recorded one
+        let mut board = B15F::from(ReplayPort::from_reader(&log[..]).unwrap()).unwrap();
+        assert_eq!(board.analog_read(3).unwrap(), 517);
+        assert!(board.into_inner().is_done());
+    }
+
+    #[test]
+    fn diverging_replay_fails() {
+        let log = record_session();
+        let mut board = B15F::from(ReplayPort::from_reader(&log[..]).unwrap()).unwrap();
+        assert!(matches!(
+            board.analog_read(4),
+            Err(B15FCommandError::IoError(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{
+    B15FCommandError, BoardInfo, Command, CommandResult, NativePort, OutputConfig, Port, B15F,
+};
+use serialport::SerialPort;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A board shared between threads, e.g. a logger reading inputs on a timer and a UI thread
+/// writing outputs.
+///
+/// Clones refer to the same board. Every call locks the board for the whole request and response,
+/// so commands from different threads are executed one after another, never interleaved.
+///
+/// Every request to the board has a wrapper here. Everything else (settings, calibration, streams,
+/// several commands in a row without other threads in between) runs on the locked board with
+/// [`SharedB15F::with`]:
+///
+/// ```no_run
+/// use b15f::{Port, SharedB15F, B15F};
+///
+/// let board = SharedB15F::new(B15F::instance().unwrap());
+/// let volts = board.with(|board| {
+///     board.analog_write(Port::Port0, 512)?;
+///     board.analog_read_voltage(0)
+/// });
+/// ```
+pub struct SharedB15F<P = NativePort>
+where
+    P: SerialPort,
+{
+    board: Arc<Mutex<B15F<P>>>,
+}
+
+impl<P> Clone for SharedB15F<P>
+where
+    P: SerialPort,
+{
+    fn clone(&self) -> Self {
+        SharedB15F {
+            board: Arc::clone(&self.board),
+        }
+    }
+}
+
+impl<P> From<B15F<P>> for SharedB15F<P>
+where
+    P: SerialPort,
+{
+    fn from(board: B15F<P>) -> Self {
+        SharedB15F::new(board)
+    }
+}
+
+impl<P> SharedB15F<P>
+where
+    P: SerialPort,
+{
+    pub fn new(board: B15F<P>) -> Self {
+        SharedB15F {
+            board: Arc::new(Mutex::new(board)),
+        }
+    }
+
+    /// Locks the board until the guard is dropped, e.g. to run several commands without other
+    /// threads in between.
+    ///
+    /// A thread panicking while holding the lock doesn't make the board unusable for the others.
+    pub fn lock(&self) -> MutexGuard<'_, B15F<P>> {
+        self.board
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs `f` with the board locked.
+    pub fn with<T>(&self, f: impl FnOnce(&mut B15F<P>) -> T) -> T {
+        f(&mut self.lock())
+    }
+
+    /// See [`B15F::execute`].
+    pub fn execute(&self, command: Command) -> Result<CommandResult, B15FCommandError> {
+        self.lock().execute(command)
+    }
+
+    /// See [`B15F::test`].
+    pub fn test(&self) -> Result<bool, B15FCommandError> {
+        self.lock().test()
+    }
+
+    /// See [`B15F::digital_write`].
+    pub fn digital_write(&self, port: Port, value: u8) -> Result<(), B15FCommandError> {
+        self.lock().digital_write(port, value)
+    }
+
+    /// See [`B15F::digital_read`].
+    pub fn digital_read(&self, port: Port) -> Result<u8, B15FCommandError> {
+        self.lock().digital_read(port)
+    }
+
+    /// See [`B15F::analog_write`].
+    pub fn analog_write(&self, port: Port, value: u16) -> Result<(), B15FCommandError> {
+        self.lock().analog_write(port, value)
+    }
+
+    /// See [`B15F::analog_read`].
+    pub fn analog_read(&self, channel: u8) -> Result<u16, B15FCommandError> {
+        self.lock().analog_read(channel)
+    }
+
+    /// See [`B15F::self_test`].
+    pub fn self_test(&self) -> Result<bool, B15FCommandError> {
+        self.lock().self_test()
+    }
+
+    /// See [`B15F::board_info`].
+    pub fn board_info(&self) -> Result<BoardInfo, B15FCommandError> {
+        self.lock().board_info()
+    }
+
+    /// See [`B15F::digital_set_bit`].
+    pub fn digital_set_bit(
+        &self,
+        port: Port,
+        bit: u8,
+        value: bool,
+    ) -> Result<(), B15FCommandError> {
+        self.lock().digital_set_bit(port, bit, value)
+    }
+
+    /// See [`B15F::read_dip_switch`].
+    pub fn read_dip_switch(&self) -> Result<u8, B15FCommandError> {
+        self.lock().read_dip_switch()
+    }
+
+    /// See [`B15F::configure_outputs`].
+    pub fn configure_outputs(&self, config: &OutputConfig) -> Result<(), B15FCommandError> {
+        self.lock().configure_outputs(config)
+    }
+
+    /// See [`B15F::read_analog_batch`].
+    pub fn read_analog_batch(&self, channels: &[u8]) -> Result<Vec<u16>, B15FCommandError> {
+        self.lock().read_analog_batch(channels)
+    }
+
+    /// See [`B15F::analog_read_all`].
+    pub fn analog_read_all(&self) -> Result<[u16; 8], B15FCommandError> {
+        self.lock().analog_read_all()
+    }
+
+    /// See [`B15F::set_pwm_frequency`].
+    pub fn set_pwm_frequency(&self, frequency: f32) -> Result<u8, B15FCommandError> {
+        self.lock().set_pwm_frequency(frequency)
+    }
+
+    /// See [`B15F::set_pwm_value`].
+    pub fn set_pwm_value(&self, value: u8) -> Result<(), B15FCommandError> {
+        self.lock().set_pwm_value(value)
+    }
+
+    /// See [`B15F::pwm_disable`].
+    pub fn pwm_disable(&self) -> Result<(), B15FCommandError> {
+        self.lock().pwm_disable()
+    }
+
+    /// See [`B15F::servo_enable`].
+    pub fn servo_enable(&self) -> Result<(), B15FCommandError> {
+        self.lock().servo_enable()
+    }
+
+    /// See [`B15F::servo_disable`].
+    pub fn servo_disable(&self) -> Result<(), B15FCommandError> {
+        self.lock().servo_disable()
+    }
+
+    /// See [`B15F::servo_set_position`].
+    pub fn servo_set_position(&self, pulse: u16) -> Result<(), B15FCommandError> {
+        self.lock().servo_set_position(pulse)
+    }
+
+    /// See [`B15F::set_counter_offset`].
+    pub fn set_counter_offset(&self, offset: u16) -> Result<(), B15FCommandError> {
+        self.lock().set_counter_offset(offset)
+    }
+}
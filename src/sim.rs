@@ -0,0 +1,265 @@
+use crate::fixed_port::fixed_serial_port;
+use crate::{
+    MSG_ERROR, MSG_OK, RQ_ADC_DAC_STROKE, RQ_ANALOG_READ, RQ_ANALOG_WRITE_0, RQ_ANALOG_WRITE_1,
+    RQ_COUNTER_OFFSET, RQ_DIGITAL_READ_0, RQ_DIGITAL_READ_1, RQ_DIGITAL_WRITE_0,
+    RQ_DIGITAL_WRITE_1, RQ_DISCARD, RQ_GET_MEM_16, RQ_GET_MEM_8, RQ_INFO, RQ_INT_TEST,
+    RQ_PWM_SET_FREQ, RQ_PWM_SET_VALUE, RQ_READ_DIP_SWITCH, RQ_SELF_TEST, RQ_SERVO_DISABLE,
+    RQ_SERVO_ENABLE, RQ_SERVO_SET_POS, RQ_SET_MEM_16, RQ_SET_MEM_8, RQ_TEST,
+};
+use rand::random;
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Parameters of the simulated wiring, see [`SimulatedB15F`].
+#[derive(Debug, Clone)]
+pub struct SimulationModel {
+    /// Maximum noise in ADC counts added to (or subtracted from) every analog reading.
+    pub noise: u16,
+    /// Time constant of the RC low pass between DAC1 and ADC1.
+    pub rc_time_constant: Duration,
+    /// Values of the analog inputs 2 to 7, which are not connected to anything else.
+    pub static_inputs: [u16; 6],
+    /// Position of the DIP switches as the firmware reports them.
+    pub dip_switch: u8,
+}
+
+impl Default for SimulationModel {
+    fn default() -> Self {
+        SimulationModel {
+            noise: 2,
+            rc_time_constant: Duration::from_millis(100),
+            static_inputs: [0; 6],
+            dip_switch: 0,
+        }
+    }
+}
+
+/// A simulated board implementing [`serialport::SerialPort`] for teaching and demos without hardware.
+///
+/// It speaks the B15F protocol and models a small experiment:
+/// * DAC0 is looped back to ADC0 with noise.
+/// * DAC1 drives ADC1 through a first order RC low pass, so readings follow a write over time.
+/// * Both digital ports are looped back, reading a port returns the byte last written to it.
+///   Like on the real board the inputs are wired in reverse bit order, which `digital_read` undoes.
+///
+/// ```no_run
+/// use b15f::sim::SimulatedB15F;
+/// use b15f::{Port, B15F};
+///
+/// let mut board = B15F::from(SimulatedB15F::default()).unwrap();
+/// board.analog_write(Port::Port0, 512).unwrap();
+/// let value = board.analog_read(0).unwrap();
+/// ```
+pub struct SimulatedB15F {
+    model: SimulationModel,
+    timeout: Duration,
+    input: Vec<u8>,
+    output: Mutex<VecDeque<u8>>,
+    state: BoardState,
+}
+
+struct BoardState {
+    dac: [u16; 2],
+    digital: [u8; 2],
+    //ADC1 voltage (in counts) at the time of the last update
+    rc_value: f32,
+    rc_updated: Instant,
+}
+
+impl SimulatedB15F {
+    pub fn new(model: SimulationModel) -> Self {
+        SimulatedB15F {
+            model,
+            timeout: Duration::ZERO,
+            input: Vec::new(),
+            output: Mutex::new(VecDeque::new()),
+            state: BoardState {
+                dac: [0; 2],
+                digital: [0; 2],
+                rc_value: 0.0,
+                rc_updated: Instant::now(),
+            },
+        }
+    }
+
+    pub fn model(&self) -> &SimulationModel {
+        &self.model
+    }
+
+    fn output(&self) -> std::sync::MutexGuard<'_, VecDeque<u8>> {
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn respond(&self, bytes: &[u8]) {
+        self.output().extend(bytes);
+    }
+
+    fn respond_u16(&self, value: u16) {
+        self.respond(&value.to_le_bytes());
+    }
+
+    fn noisy(&self, value: f32) -> u16 {
+        let noise = self.model.noise as i32;
+        let offset = if noise == 0 {
+            0
+        } else {
+            (random::<u32>() % (2 * noise as u32 + 1)) as i32 - noise
+        };
+        (value.round() as i32 + offset).clamp(0, 1023) as u16
+    }
+
+    fn update_rc(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.state.rc_updated).as_secs_f32();
+        let tau = self.model.rc_time_constant.as_secs_f32();
+        let target = self.state.dac[1] as f32;
+        self.state.rc_value = if tau <= 0.0 {
+            target
+        } else {
+            target + (self.state.rc_value - target) * (-elapsed / tau).exp()
+        };
+        self.state.rc_updated = now;
+    }
+
+    fn sample(&mut self, channel: u8) -> u16 {
+        match channel {
+            0 => self.noisy(self.state.dac[0] as f32),
+            1 => {
+                self.update_rc();
+                self.noisy(self.state.rc_value)
+            }
+            2..=7 => self.noisy(self.model.static_inputs[channel as usize - 2] as f32),
+            _ => 0xFFFF,
+        }
+    }
+
+    fn set_dac(&mut self, index: usize, value: u16) {
+        if index == 1 {
+            //settle the low pass up to now before the input changes
+            self.update_rc();
+        }
+        self.state.dac[index] = value;
+    }
+
+    /// Length of the request starting with `request` including the request byte itself.
+    fn request_len(request: u8) -> usize {
+        match request {
+            RQ_TEST | RQ_DIGITAL_WRITE_0 | RQ_DIGITAL_WRITE_1 | RQ_PWM_SET_VALUE => 2,
+            RQ_INT_TEST | RQ_ANALOG_WRITE_0 | RQ_ANALOG_WRITE_1 => 3,
+            RQ_COUNTER_OFFSET | RQ_SERVO_SET_POS => 3,
+            RQ_ANALOG_READ => 2,
+            RQ_ADC_DAC_STROKE => 9,
+            RQ_PWM_SET_FREQ => 5,
+            RQ_SET_MEM_8 => 4,
+            RQ_GET_MEM_8 | RQ_GET_MEM_16 => 3,
+            RQ_SET_MEM_16 => 5,
+            _ => 1,
+        }
+    }
+
+    fn process(&mut self) {
+        while let Some(&request) = self.input.first() {
+            let len = Self::request_len(request);
+            if self.input.len() < len {
+                return;
+            }
+            let data: Vec<u8> = self.input.drain(..len).collect();
+            self.execute(&data);
+        }
+    }
+
+    fn execute(&mut self, data: &[u8]) {
+        let u16_at = |index: usize| u16::from_le_bytes([data[index], data[index + 1]]);
+        match data[0] {
+            RQ_DISCARD => {}
+            RQ_TEST => self.respond(&[MSG_OK, data[1]]),
+            RQ_INFO => {
+                self.respond(&[3]);
+                for info in ["Jan  1 1970", "00:00:00", "simulated"] {
+                    self.respond(&[info.len() as u8 + 1]);
+                    self.respond(info.as_bytes());
+                    self.respond(&[0]);
+                }
+                self.respond(&[MSG_OK]);
+            }
+            RQ_INT_TEST => self.respond_u16(u16_at(1).wrapping_mul(3)),
+            RQ_SELF_TEST => self.respond(&[MSG_OK]),
+            RQ_DIGITAL_WRITE_0 | RQ_DIGITAL_WRITE_1 => {
+                self.state.digital[(data[0] - RQ_DIGITAL_WRITE_0) as usize] = data[1];
+                self.respond(&[MSG_OK]);
+            }
+            RQ_DIGITAL_READ_0 | RQ_DIGITAL_READ_1 => {
+                let value = self.state.digital[(data[0] - RQ_DIGITAL_READ_0) as usize];
+                self.respond(&[value.reverse_bits()])
+            }
+            RQ_READ_DIP_SWITCH => self.respond(&[self.model.dip_switch]),
+            RQ_ANALOG_WRITE_0 | RQ_ANALOG_WRITE_1 => {
+                let value = u16_at(1);
+                if value > 1023 {
+                    self.respond(&[MSG_ERROR]);
+                } else {
+                    self.set_dac((data[0] - RQ_ANALOG_WRITE_0) as usize, value);
+                    self.respond(&[MSG_OK]);
+                }
+            }
+            RQ_ANALOG_READ => {
+                let value = self.sample(data[1]);
+                self.respond_u16(value);
+            }
+            RQ_ADC_DAC_STROKE => {
+                let (channel_a, channel_b) = (data[1], data[2]);
+                let start = u16_at(3);
+                let delta = u16_at(5) as i16;
+                let count = u16_at(7);
+                for step in 0..count {
+                    let value = (start as i32 + delta as i32 * step as i32).clamp(0, 1023);
+                    self.set_dac(0, value as u16);
+                    let a = self.sample(channel_a);
+                    let b = self.sample(channel_b);
+                    self.respond_u16(a);
+                    self.respond_u16(b);
+                }
+                self.respond(&[MSG_OK]);
+            }
+            //answers with the timer top value
+            RQ_PWM_SET_FREQ => self.respond(&[0xFF]),
+            RQ_PWM_SET_VALUE | RQ_COUNTER_OFFSET | RQ_SERVO_ENABLE | RQ_SERVO_DISABLE
+            | RQ_SERVO_SET_POS => self.respond(&[MSG_OK]),
+            //writes echo the written value, reads return zero
+            RQ_SET_MEM_8 => self.respond(&[data[3]]),
+            RQ_GET_MEM_8 => self.respond(&[0]),
+            RQ_SET_MEM_16 => self.respond(&data[3..5]),
+            RQ_GET_MEM_16 => self.respond_u16(0),
+            _ => self.respond(&[MSG_ERROR]),
+        }
+    }
+}
+
+impl Default for SimulatedB15F {
+    fn default() -> Self {
+        SimulatedB15F::new(SimulationModel::default())
+    }
+}
+
+impl io::Write for SimulatedB15F {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        self.process();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fixed_serial_port!(
+    SimulatedB15F,
+    "simulated",
+    "a simulated board can't be cloned",
+    "simulated board has nothing to send"
+);
@@ -0,0 +1,113 @@
+use crate::{B15FCommandError, B15F};
+use serialport::SerialPort;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Endless iterator of analog readings, see [`B15F::analog_stream`].
+pub struct AnalogStream<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+    channel: u8,
+    interval: Duration,
+    next_sample: Option<Instant>,
+    finished: bool,
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Reads an analog input over and over, e.g. for data logging with `.take(1000)`.
+    ///
+    /// Invalid readings are yielded as errors and the stream continues, any other error (like an
+    /// IO error of a disconnected board) is yielded once and ends the stream.
+    pub fn analog_stream(&mut self, channel: u8) -> AnalogStream<'_, P> {
+        AnalogStream {
+            board: self,
+            channel,
+            interval: Duration::ZERO,
+            next_sample: None,
+            finished: false,
+        }
+    }
+
+    /// Samples analog inputs `count` times every `interval` and writes them to `out` as CSV.
+    ///
+    /// The first row is a header, every further row holds the seconds since the first sample
+    /// followed by one column per channel. The channels of a row are read pipelined like
+    /// [`B15F::read_analog_batch`]. Samples are scheduled on a monotonic clock from the start, so
+    /// the time spent reading doesn't add up. If a read takes longer than the interval, the
+    /// next sample is taken right away and the schedule continues from there. Every row is
+    /// flushed, so a killed process leaves a valid partial file.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::read_analog_batch`], logging stops at the first error.
+    /// * If writing to `out` fails, the function will return a B15FCommandError::IoError.
+    pub fn log_analog<W: Write>(
+        &mut self,
+        ports: &[u8],
+        interval: Duration,
+        count: usize,
+        mut out: W,
+    ) -> Result<(), B15FCommandError> {
+        let header: Vec<String> = ports.iter().map(|port| format!("a{}", port)).collect();
+        writeln!(out, "time,{}", header.join(","))?;
+        out.flush()?;
+        let started = Instant::now();
+        let mut next_sample = started;
+        for _ in 0..count {
+            std::thread::sleep(next_sample.saturating_duration_since(Instant::now()));
+            let sampled = Instant::now();
+            let values = self.read_analog_batch(ports)?;
+            let values: Vec<String> = values.iter().map(u16::to_string).collect();
+            writeln!(
+                out,
+                "{:.6},{}",
+                sampled.duration_since(started).as_secs_f64(),
+                values.join(",")
+            )?;
+            out.flush()?;
+            next_sample = (next_sample + interval).max(Instant::now());
+        }
+        Ok(())
+    }
+}
+
+impl<P> AnalogStream<'_, P>
+where
+    P: SerialPort,
+{
+    /// Takes a sample every `interval` instead of as fast as possible.
+    ///
+    /// The interval is measured from the start of one read to the next, a read taking longer
+    /// delays the following samples.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<P> Iterator for AnalogStream<'_, P>
+where
+    P: SerialPort,
+{
+    type Item = Result<u16, B15FCommandError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        if let Some(next_sample) = self.next_sample {
+            std::thread::sleep(next_sample.saturating_duration_since(Instant::now()));
+        }
+        self.next_sample = Some(Instant::now() + self.interval);
+        let result = self.board.analog_read(self.channel);
+        if let Err(err) = &result {
+            self.finished = !matches!(err, B15FCommandError::B15FError);
+        }
+        Some(result)
+    }
+}
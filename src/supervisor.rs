@@ -0,0 +1,261 @@
+use crate::{AsyncB15F, B15FCommandError, B15FInitError, Port, DEFAULT_TIMEOUT};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::{watch, Mutex, MutexGuard};
+use tokio_serial::SerialStream;
+
+type BoardFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, B15FCommandError>> + Send + 'a>>;
+
+/// The connection of a [`SupervisedB15F`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ConnectionState {
+    /// The board answered the last command.
+    Connected,
+    /// The connection broke and the port is reopened in the background.
+    Reconnecting {
+        /// The reconnect attempts which failed so far.
+        attempts: u32,
+    },
+    /// Every reconnect attempt failed, see [`SupervisorConfig::max_attempts`] and
+    /// [`SupervisedB15F::restart`].
+    Failed,
+}
+
+/// What commands do while a [`SupervisedB15F`] is not connected.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum WhileDisconnected {
+    /// Wait until the board is connected again, fail once the supervisor gave up.
+    #[default]
+    Queue,
+    /// Fail with [`B15FCommandError::Disconnected`] right away.
+    FailFast,
+}
+
+/// How a [`SupervisedB15F`] reconnects.
+///
+/// The first attempt waits `initial_backoff`, every further attempt waits twice as long as the
+/// previous one up to `max_backoff`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SupervisorConfig {
+    /// The pause before the first reconnect attempt.
+    pub initial_backoff: Duration,
+    /// The longest pause between two reconnect attempts.
+    pub max_backoff: Duration,
+    /// The attempts after which the supervisor gives up, `None` to retry forever.
+    pub max_attempts: Option<u32>,
+    /// What commands do while the board is not connected.
+    pub while_disconnected: WhileDisconnected,
+    /// See [`AsyncB15F::set_timeout`], applies to the reopened board as well.
+    pub timeout: Duration,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        SupervisorConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            while_disconnected: WhileDisconnected::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// An async board which reconnects by itself, e.g. for dashboard services running for weeks.
+///
+/// An IO error, a timeout or a serial port error means the connection broke: the failed command
+/// returns its error, the board is dropped and the port is reopened by name in a background task
+/// with exponential backoff. Other errors (like an invalid channel) leave the connection alone.
+/// Follow the connection with [`SupervisedB15F::state_changes`].
+///
+/// Clones refer to the same board, commands from different tasks are executed one after another.
+/// The background task needs a tokio runtime with the time driver enabled and stops once every
+/// clone was dropped.
+#[derive(Clone)]
+pub struct SupervisedB15F {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    port_name: String,
+    config: SupervisorConfig,
+    board: Mutex<Option<AsyncB15F<SerialStream>>>,
+    state: watch::Sender<ConnectionState>,
+}
+
+impl AsyncB15F<SerialStream> {
+    /// Opens the board on the given port and supervises the connection, see [`SupervisedB15F`].
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`AsyncB15F::open_port`], the first connection is not retried.
+    pub async fn supervised(
+        port_name: &str,
+        config: SupervisorConfig,
+    ) -> Result<SupervisedB15F, B15FInitError> {
+        let mut board = AsyncB15F::open_port(port_name).await?;
+        board.set_timeout(config.timeout);
+        let (state, _) = watch::channel(ConnectionState::Connected);
+        Ok(SupervisedB15F {
+            shared: Arc::new(Shared {
+                port_name: port_name.to_string(),
+                config,
+                board: Mutex::new(Some(board)),
+                state,
+            }),
+        })
+    }
+}
+
+impl SupervisedB15F {
+    /// Returns the current state of the connection.
+    pub fn state(&self) -> ConnectionState {
+        *self.shared.state.borrow()
+    }
+
+    /// Returns a receiver which is notified about every state change, e.g. to show the connection
+    /// in a UI with `while receiver.changed().await.is_ok()`.
+    pub fn state_changes(&self) -> watch::Receiver<ConnectionState> {
+        self.shared.state.subscribe()
+    }
+
+    /// Starts reconnecting again after the supervisor gave up, does nothing in any other state.
+    pub fn restart(&self) {
+        let restarted = self.shared.state.send_if_modified(|state| {
+            let failed = *state == ConnectionState::Failed;
+            if failed {
+                *state = ConnectionState::Reconnecting { attempts: 0 };
+            }
+            failed
+        });
+        if restarted {
+            tokio::spawn(reconnect(Arc::downgrade(&self.shared)));
+        }
+    }
+
+    /// See [`AsyncB15F::test`].
+    pub async fn test(&self) -> Result<bool, B15FCommandError> {
+        self.command(|board| Box::pin(board.test())).await
+    }
+
+    /// See [`AsyncB15F::digital_write`].
+    pub async fn digital_write(&self, port: Port, value: u8) -> Result<(), B15FCommandError> {
+        self.command(|board| Box::pin(board.digital_write(port, value)))
+            .await
+    }
+
+    /// See [`AsyncB15F::digital_read`].
+    pub async fn digital_read(&self, port: Port) -> Result<u8, B15FCommandError> {
+        self.command(|board| Box::pin(board.digital_read(port)))
+            .await
+    }
+
+    /// See [`AsyncB15F::analog_write`].
+    pub async fn analog_write(&self, port: Port, value: u16) -> Result<(), B15FCommandError> {
+        self.command(|board| Box::pin(board.analog_write(port, value)))
+            .await
+    }
+
+    /// See [`AsyncB15F::analog_read`].
+    pub async fn analog_read(&self, channel: u8) -> Result<u16, B15FCommandError> {
+        self.command(|board| Box::pin(board.analog_read(channel)))
+            .await
+    }
+
+    /// Runs a command on the connected board and starts reconnecting if the connection broke.
+    async fn command<T>(
+        &self,
+        command: impl for<'a> FnOnce(&'a mut AsyncB15F<SerialStream>) -> BoardFuture<'a, T>,
+    ) -> Result<T, B15FCommandError> {
+        let mut board = self.connected().await?;
+        let result = match board.as_mut() {
+            Some(connected) => command(connected).await,
+            None => Err(B15FCommandError::Disconnected),
+        };
+        if let Err(err) = &result {
+            if breaks_connection(err) {
+                //the state changes while the lock is held, so nobody sees a connected state without a board
+                *board = None;
+                self.shared
+                    .state
+                    .send_replace(ConnectionState::Reconnecting { attempts: 0 });
+                tokio::spawn(reconnect(Arc::downgrade(&self.shared)));
+            }
+        }
+        result
+    }
+
+    /// Locks the board once it is connected, or fails right away if configured so.
+    async fn connected(
+        &self,
+    ) -> Result<MutexGuard<'_, Option<AsyncB15F<SerialStream>>>, B15FCommandError> {
+        let mut changes = self.shared.state.subscribe();
+        loop {
+            let state = *changes.borrow_and_update();
+            match state {
+                ConnectionState::Connected => {
+                    let board = self.shared.board.lock().await;
+                    if board.is_some() {
+                        return Ok(board);
+                    }
+                    //the connection broke while waiting for the lock, the state changed already
+                    continue;
+                }
+                ConnectionState::Reconnecting { .. }
+                    if self.shared.config.while_disconnected == WhileDisconnected::Queue => {}
+                _ => return Err(B15FCommandError::Disconnected),
+            }
+            changes
+                .changed()
+                .await
+                .map_err(|_| B15FCommandError::Disconnected)?;
+        }
+    }
+}
+
+/// Reopens the port until it works or the supervisor gives up, stops when the supervisor is gone.
+async fn reconnect(shared: Weak<Shared>) {
+    let Some(config) = shared.upgrade().map(|shared| shared.config) else {
+        return;
+    };
+    let mut backoff = config.initial_backoff;
+    let mut attempts = 0;
+    loop {
+        tokio::time::sleep(backoff).await;
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        match AsyncB15F::open_port(&shared.port_name).await {
+            Ok(mut board) => {
+                board.set_timeout(config.timeout);
+                let mut slot = shared.board.lock().await;
+                *slot = Some(board);
+                shared.state.send_replace(ConnectionState::Connected);
+                return;
+            }
+            Err(_) => {
+                attempts += 1;
+                if config.max_attempts.is_some_and(|max| attempts >= max) {
+                    shared.state.send_replace(ConnectionState::Failed);
+                    return;
+                }
+                shared
+                    .state
+                    .send_replace(ConnectionState::Reconnecting { attempts });
+                backoff = backoff.saturating_mul(2).min(config.max_backoff);
+            }
+        }
+    }
+}
+
+/// Whether an error means the board is gone, as opposed to a rejected or invalid command.
+fn breaks_connection(err: &B15FCommandError) -> bool {
+    matches!(
+        err,
+        B15FCommandError::IoError(_)
+            | B15FCommandError::Timeout(_)
+            | B15FCommandError::SerialPortError(_)
+    )
+}
@@ -0,0 +1,171 @@
+//! A scripted serial port to test code using [`crate::B15F`] without a board.
+//!
+//! ```
+//! use b15f::testing::MockPort;
+//! use b15f::{Port, B15F};
+//!
+//! let port = MockPort::new()
+//!     .expect_test()
+//!     .expect_write(&[5, 0xAA])
+//!     .reply(&[0xFF]);
+//! let mut board = B15F::from(port).unwrap();
+//! board.digital_write(Port::Port0, 0xAA).unwrap();
+//! ```
+
+use crate::fixed_port::{fixed_serial_port, read_output};
+use crate::{MSG_OK, RQ_TEST};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug)]
+enum Step {
+    Write(Vec<u8>),
+    Reply(Vec<u8>),
+    Test,
+    Stall,
+}
+
+/// A serial port following a script of expected writes and canned replies.
+///
+/// Replies become readable once every write scripted before them was received. A write not
+/// matching the script panics, reading with nothing to read times out like a silent board.
+/// Dropping the port panics if the script wasn't completed, so a test fails when an expected
+/// request was never sent.
+#[derive(Debug, Default)]
+pub struct MockPort {
+    script: VecDeque<Step>,
+    written: Vec<u8>,
+    output: Mutex<VecDeque<u8>>,
+    timeout: Duration,
+}
+
+impl MockPort {
+    pub fn new() -> Self {
+        MockPort::default()
+    }
+
+    /// Expects the given bytes to be written next.
+    pub fn expect_write(mut self, bytes: &[u8]) -> Self {
+        self.script.push_back(Step::Write(bytes.to_vec()));
+        self
+    }
+
+    /// Answers with the given bytes after the previous writes.
+    pub fn reply(mut self, bytes: &[u8]) -> Self {
+        self.script.push_back(Step::Reply(bytes.to_vec()));
+        self.advance();
+        self
+    }
+
+    /// Lets the next read time out once before the following replies arrive, like a slow adapter
+    /// splitting a response.
+    pub fn stall(mut self) -> Self {
+        self.script.push_back(Step::Stall);
+        self
+    }
+
+    /// Expects a test request and answers it successfully.
+    ///
+    /// The test request contains a random byte which the board has to echo, so it can't be
+    /// scripted with [`MockPort::expect_write`]. [`crate::B15F::from`] starts with a test request,
+    /// so most scripts start with this, unless the board is created with
+    /// [`crate::B15F::with_port_unchecked`].
+    pub fn expect_test(mut self) -> Self {
+        self.script.push_back(Step::Test);
+        self
+    }
+
+    /// Returns whether every scripted step was completed.
+    pub fn is_done(&self) -> bool {
+        self.script.is_empty() && self.written.is_empty()
+    }
+
+    fn output(&self) -> std::sync::MutexGuard<'_, VecDeque<u8>> {
+        self.output
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn advance(&mut self) {
+        loop {
+            match self.script.front() {
+                Some(Step::Reply(bytes)) => {
+                    let bytes = bytes.clone();
+                    self.output().extend(bytes);
+                }
+                Some(Step::Write(expected)) => {
+                    let len = expected.len().min(self.written.len());
+                    assert_eq!(
+                        &self.written[..len],
+                        &expected[..len],
+                        "unexpected write to mock port"
+                    );
+                    if len < expected.len() {
+                        return;
+                    }
+                    self.written.drain(..len);
+                }
+                Some(Step::Test) => {
+                    if let Some(&request) = self.written.first() {
+                        assert_eq!(request, RQ_TEST, "expected a test request on mock port");
+                    }
+                    if self.written.len() < 2 {
+                        return;
+                    }
+                    let echo = self.written[1];
+                    self.written.drain(..2);
+                    self.output().extend([MSG_OK, echo]);
+                }
+                Some(Step::Stall) => return,
+                None => {
+                    assert!(
+                        self.written.is_empty(),
+                        "unexpected write to mock port after the script ended: {:?}",
+                        self.written
+                    );
+                    return;
+                }
+            }
+            self.script.pop_front();
+        }
+    }
+}
+
+impl Drop for MockPort {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            assert!(
+                self.script.is_empty(),
+                "mock port dropped before the script was completed: {:?}",
+                self.script
+            );
+        }
+    }
+}
+
+impl io::Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        self.advance();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.output().is_empty() && matches!(self.script.front(), Some(Step::Stall)) {
+            self.script.pop_front();
+            self.advance();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "mock port stalled"));
+        }
+        read_output(&mut self.output(), buf, "mock port has nothing to send")
+    }
+}
+
+fixed_serial_port!(MockPort, "mock", "a mock port can't be cloned");
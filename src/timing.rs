@@ -0,0 +1,108 @@
+use crate::{B15FCommandError, Port, B15F};
+use serialport::SerialPort;
+use std::time::{Duration, Instant};
+
+/// A value read from the board together with when it was read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Timestamped<T> {
+    pub value: T,
+    /// When the host received the response.
+    pub received: Instant,
+    /// Estimate of when the board took the sample, `received` minus half the measured round trip.
+    /// Equal to `received` as long as [`B15F::measure_latency`] was not called.
+    pub sampled: Instant,
+}
+
+/// Statistics of the round trips timed by [`B15F::measure_latency`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LatencyStats {
+    /// The number of timed round trips.
+    pub samples: u32,
+    /// The fastest round trip.
+    pub min: Duration,
+    /// The slowest round trip.
+    pub max: Duration,
+    /// The average round trip, also stored as [`B15F::latency`].
+    pub mean: Duration,
+    /// The standard deviation, how much the round trips jitter.
+    pub stddev: Duration,
+}
+
+impl<P> B15F<P>
+where
+    P: SerialPort,
+{
+    /// Measures the serial round-trip latency by timing `samples` `test` requests (at least one).
+    ///
+    /// Comparing the statistics quantifies e.g. what pipelining like `experiment_read_many` saves
+    /// on a given adapter. The mean is stored and used by the timestamped reads to estimate when the board actually
+    /// took a sample. This assumes the latency is symmetric (the request needs as long to reach the
+    /// board as the response to come back) and that the board answers immediately, which is close
+    /// enough for USB serial adapters but not exact.
+    ///
+    /// # Errors
+    ///
+    /// * Same as [`B15F::test`].
+    /// * If the board answers a test with a wrong value, the function will return a B15FCommandError::B15FError.
+    pub fn measure_latency(&mut self, samples: u32) -> Result<LatencyStats, B15FCommandError> {
+        let samples = samples.max(1);
+        let mut round_trips = Vec::with_capacity(samples as usize);
+        for _ in 0..samples {
+            let started = Instant::now();
+            if !self.test()? {
+                return Err(B15FCommandError::B15FError);
+            }
+            round_trips.push(started.elapsed());
+        }
+        let mean = round_trips.iter().sum::<Duration>() / samples;
+        let variance = round_trips
+            .iter()
+            .map(|round_trip| (round_trip.as_secs_f64() - mean.as_secs_f64()).powi(2))
+            .sum::<f64>()
+            / samples as f64;
+        self.latency = Some(mean);
+        Ok(LatencyStats {
+            samples,
+            min: round_trips.iter().copied().min().unwrap_or_default(),
+            max: round_trips.iter().copied().max().unwrap_or_default(),
+            mean,
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+
+    /// The round-trip latency measured by [`B15F::measure_latency`].
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Like [`B15F::analog_read`] but also returns when the value was read.
+    pub fn analog_read_timestamped(
+        &mut self,
+        port: u8,
+    ) -> Result<Timestamped<u16>, B15FCommandError> {
+        let value = self.analog_read(port)?;
+        Ok(self.timestamp(value))
+    }
+
+    /// Like [`B15F::digital_read`] but also returns when the value was read.
+    pub fn digital_read_timestamped(
+        &mut self,
+        port: Port,
+    ) -> Result<Timestamped<u8>, B15FCommandError> {
+        let value = self.digital_read(port)?;
+        Ok(self.timestamp(value))
+    }
+
+    fn timestamp<T>(&self, value: T) -> Timestamped<T> {
+        let received = Instant::now();
+        let sampled = self
+            .latency
+            .and_then(|latency| received.checked_sub(latency / 2))
+            .unwrap_or(received);
+        Timestamped {
+            value,
+            received,
+            sampled,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Handles encoding the order of multi-step commands in the type system.
+//!
+//! The firmware rejects a servo position before the servo was enabled and a PWM value before a
+//! frequency was set. With these handles the wrong order doesn't compile instead of failing at runtime.
+
+use crate::{B15FCommandError, B15F};
+use serialport::SerialPort;
+
+/// The servo output before it was enabled, see [`B15F::servo`].
+pub struct ServoDisabled<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+}
+
+/// The enabled servo output.
+pub struct ServoEnabled<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+}
+
+impl<'a, P> ServoDisabled<'a, P>
+where
+    P: SerialPort,
+{
+    pub(crate) fn new(board: &'a mut B15F<P>) -> Self {
+        ServoDisabled { board }
+    }
+
+    /// Enables the servo output.
+    pub fn enable(self) -> Result<ServoEnabled<'a, P>, B15FCommandError> {
+        self.board.servo_enable()?;
+        Ok(ServoEnabled { board: self.board })
+    }
+}
+
+impl<'a, P> ServoEnabled<'a, P>
+where
+    P: SerialPort,
+{
+    /// Sets the servo pulse length in microseconds.
+    ///
+    /// # Errors
+    ///
+    /// * If the pulse length is greater than 19000, the function will return a B15FCommandError::ValueOutOfRange.
+    pub fn set_position(&mut self, pulse: u16) -> Result<(), B15FCommandError> {
+        self.board.servo_set_position(pulse)
+    }
+
+    /// Disables the servo output again.
+    pub fn disable(self) -> Result<ServoDisabled<'a, P>, B15FCommandError> {
+        self.board.servo_disable()?;
+        Ok(ServoDisabled { board: self.board })
+    }
+}
+
+/// The PWM output before a frequency was set, see [`B15F::pwm`].
+pub struct PwmUnconfigured<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+}
+
+/// The PWM output with a frequency set.
+pub struct PwmConfigured<'a, P>
+where
+    P: SerialPort,
+{
+    board: &'a mut B15F<P>,
+    top: u8,
+}
+
+impl<'a, P> PwmUnconfigured<'a, P>
+where
+    P: SerialPort,
+{
+    pub(crate) fn new(board: &'a mut B15F<P>) -> Self {
+        PwmUnconfigured { board }
+    }
+
+    /// Sets the PWM frequency, see [`B15F::set_pwm_frequency`].
+    pub fn set_frequency(self, frequency: f32) -> Result<PwmConfigured<'a, P>, B15FCommandError> {
+        let top = self.board.set_pwm_frequency(frequency)?;
+        Ok(PwmConfigured {
+            board: self.board,
+            top,
+        })
+    }
+}
+
+impl<P> PwmConfigured<'_, P>
+where
+    P: SerialPort,
+{
+    /// Changes the PWM frequency.
+    pub fn set_frequency(&mut self, frequency: f32) -> Result<(), B15FCommandError> {
+        self.top = self.board.set_pwm_frequency(frequency)?;
+        Ok(())
+    }
+
+    /// Sets the PWM value, the duty cycle is `value / top`.
+    pub fn set_value(&mut self, value: u8) -> Result<(), B15FCommandError> {
+        self.board.set_pwm_value(value)
+    }
+
+    /// The timer top value the board answered to the last frequency.
+    pub fn top(&self) -> u8 {
+        self.top
+    }
+}